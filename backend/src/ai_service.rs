@@ -2,19 +2,62 @@ use anyhow::{anyhow, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{info, warn};
 
+use crate::providers::{ChatProvider, CompletionRequest, OpenAiCompatProvider, OpenRouterProvider, VertexAiProvider};
+
 #[derive(Debug, Clone)]
 pub struct AiService {
     client: Client,
     openrouter_key: String,
+    models: Vec<ModelConfig>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<Value>>,
+}
+
+impl ChatMessage {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self { role: role.into(), content: content.into(), tool_call_id: None, tool_calls: None }
+    }
+}
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A callable the model can invoke mid-conversation. `requires_approval` marks
+/// side-effecting tools so the caller can gate execution before dispatch.
+#[derive(Clone)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+    pub requires_approval: bool,
+    pub handler: Arc<dyn Fn(Value) -> BoxFuture<'static, Result<Value>> + Send + Sync>,
+}
+
+impl Tool {
+    fn to_openai_schema(&self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,15 +67,109 @@ pub struct AiResponse {
     pub provider: String,
 }
 
-#[derive(Debug, Clone)]
+/// Which `ChatProvider` implementation a `ModelConfig` should be routed to. Kept distinct
+/// from `ModelConfig::provider` (a free-text display label). Configs loaded as JSON name it
+/// explicitly; `ModelConfig::new` infers it from the endpoint URL.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    OpenRouter,
+    OpenAiCompat,
+    Vertex,
+}
+
+/// A single fallback entry. Owned `String` fields (rather than the `&'static str` this used
+/// to hardcode) so operators can load, reorder, or override models from a config file or
+/// environment without recompiling the binary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
-    pub name: &'static str,
-    pub provider: &'static str,
-    pub endpoint: &'static str,
+    pub name: String,
+    pub provider: String,
+    pub kind: ProviderKind,
+    pub endpoint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertex_project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertex_location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertex_credentials_path: Option<String>,
+    pub max_tokens: u32,
+    pub temperature: f32,
+    pub top_p: f32,
+}
+
+impl ModelConfig {
+    /// Builds an OpenRouter or generic OpenAI-compatible entry, inferring `kind` from the
+    /// endpoint (OpenRouter's own host vs. anything else speaking the same wire format).
+    pub fn new(name: impl Into<String>, provider: impl Into<String>, endpoint: impl Into<String>) -> Self {
+        let endpoint = endpoint.into();
+        let kind = if endpoint.starts_with("https://openrouter.ai") {
+            ProviderKind::OpenRouter
+        } else {
+            ProviderKind::OpenAiCompat
+        };
+        Self {
+            name: name.into(),
+            provider: provider.into(),
+            kind,
+            endpoint,
+            api_key: None,
+            vertex_project_id: None,
+            vertex_location: None,
+            vertex_credentials_path: None,
+            max_tokens: 4000,
+            temperature: 0.7,
+            top_p: 0.9,
+        }
+    }
+
+    /// Builds a native Google Vertex AI entry, authenticated via an ADC service-account file.
+    pub fn vertex(
+        name: impl Into<String>,
+        project_id: impl Into<String>,
+        location: impl Into<String>,
+        credentials_path: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            provider: "Google Vertex AI".to_string(),
+            kind: ProviderKind::Vertex,
+            endpoint: String::new(),
+            api_key: None,
+            vertex_project_id: Some(project_id.into()),
+            vertex_location: Some(location.into()),
+            vertex_credentials_path: Some(credentials_path.into()),
+            max_tokens: 4000,
+            temperature: 0.7,
+            top_p: 0.9,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = top_p;
+        self
+    }
 }
 
 impl AiService {
-    pub fn new(openrouter_key: String) -> Self {
+    pub fn new(openrouter_key: String, models: Vec<ModelConfig>) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(60))
             .build()
@@ -41,55 +178,122 @@ impl AiService {
         Self {
             client,
             openrouter_key,
+            models,
         }
     }
 
-    // Primary models with fallbacks: Claude 4 Sonnet 1M -> GPT 5 -> Gemini 2.5 Pro/Flash Lite
-    fn get_model_fallbacks() -> Vec<ModelConfig> {
+    /// The stale defaults this service shipped with before model configuration was
+    /// externalized; callers load real config from a file or env instead where possible.
+    pub fn default_models() -> Vec<ModelConfig> {
         vec![
-            // Primary: Claude 4 Sonnet 1M (using latest Claude Sonnet)
-            ModelConfig {
-                name: "anthropic/claude-3-5-sonnet-20241022",
-                provider: "Anthropic (OpenRouter)",
-                endpoint: "https://openrouter.ai/api/v1/chat/completions",
-            },
-            // Secondary: GPT 5 (using latest GPT-4 until GPT-5 is available)
-            ModelConfig {
-                name: "openai/gpt-4o-2024-11-20",
-                provider: "OpenAI (OpenRouter)",
-                endpoint: "https://openrouter.ai/api/v1/chat/completions",
-            },
-            // Tertiary: Gemini 2.5 Pro (using latest Gemini Pro)
-            ModelConfig {
-                name: "google/gemini-pro-1.5-latest",
-                provider: "Google (OpenRouter)",
-                endpoint: "https://openrouter.ai/api/v1/chat/completions",
-            },
-            // Quaternary: Gemini 2.5 Flash Lite (using latest Gemini Flash)
-            ModelConfig {
-                name: "google/gemini-flash-1.5-8b",
-                provider: "Google (OpenRouter)",
-                endpoint: "https://openrouter.ai/api/v1/chat/completions",
-            },
+            ModelConfig::new(
+                "anthropic/claude-3-5-sonnet-20241022",
+                "Anthropic (OpenRouter)",
+                "https://openrouter.ai/api/v1/chat/completions",
+            ),
+            ModelConfig::new(
+                "openai/gpt-4o-2024-11-20",
+                "OpenAI (OpenRouter)",
+                "https://openrouter.ai/api/v1/chat/completions",
+            ),
+            ModelConfig::new(
+                "google/gemini-pro-1.5-latest",
+                "Google (OpenRouter)",
+                "https://openrouter.ai/api/v1/chat/completions",
+            ),
+            ModelConfig::new(
+                "google/gemini-flash-1.5-8b",
+                "Google (OpenRouter)",
+                "https://openrouter.ai/api/v1/chat/completions",
+            ),
         ]
     }
 
+    pub fn models(&self) -> &[ModelConfig] {
+        &self.models
+    }
+
+    /// Loads the fallback list from the `AI_MODEL_CONFIG_JSON` env var (a JSON array of
+    /// `ModelConfig`), falling back to `default_models()` when it's unset or invalid.
+    pub fn load_model_configs_from_env() -> Vec<ModelConfig> {
+        match std::env::var("AI_MODEL_CONFIG_JSON") {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                warn!("Invalid AI_MODEL_CONFIG_JSON, falling back to defaults: {}", e);
+                Self::default_models()
+            }),
+            Err(_) => Self::default_models(),
+        }
+    }
+
+    pub fn push_model(&mut self, model: ModelConfig) -> &mut Self {
+        self.models.push(model);
+        self
+    }
+
+    /// Reorders `self.models` to match `order` (matched by name); unlisted models sort last
+    /// in their existing relative order.
+    pub fn reorder_models(&mut self, order: &[&str]) -> &mut Self {
+        self.models
+            .sort_by_key(|m| order.iter().position(|name| *name == m.name).unwrap_or(usize::MAX));
+        self
+    }
+
+    fn provider_for(&self, model: &ModelConfig) -> Result<Box<dyn ChatProvider>> {
+        match model.kind {
+            ProviderKind::OpenRouter => {
+                let key = model.api_key.clone().unwrap_or_else(|| self.openrouter_key.clone());
+                Ok(Box::new(OpenRouterProvider::new(self.client.clone(), key)))
+            }
+            ProviderKind::OpenAiCompat => {
+                let api_base = model.endpoint.trim_end_matches("/chat/completions").to_string();
+                Ok(Box::new(OpenAiCompatProvider::new(
+                    self.client.clone(),
+                    api_base,
+                    model.api_key.clone().unwrap_or_default(),
+                    Vec::new(),
+                )))
+            }
+            ProviderKind::Vertex => {
+                let project_id = model
+                    .vertex_project_id
+                    .clone()
+                    .ok_or_else(|| anyhow!("model {} is kind=vertex but missing vertex_project_id", model.name))?;
+                let credentials_path = model
+                    .vertex_credentials_path
+                    .clone()
+                    .ok_or_else(|| anyhow!("model {} is kind=vertex but missing vertex_credentials_path", model.name))?;
+                let location = model.vertex_location.clone().unwrap_or_else(|| "us-central1".to_string());
+                Ok(Box::new(VertexAiProvider::new(self.client.clone(), project_id, location, credentials_path)))
+            }
+        }
+    }
+
     pub async fn generate_response(&self, messages: Vec<ChatMessage>) -> Result<AiResponse> {
-        let models = Self::get_model_fallbacks();
         let mut last_error = None;
 
-        for model in models {
-            match self.try_model(&model, &messages).await {
+        for model in &self.models {
+            let provider = match self.provider_for(model) {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!("Failed to build provider for {}: {}", model.name, e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            let req = CompletionRequest {
+                model: model.name.clone(),
+                messages: messages.clone(),
+                max_tokens: model.max_tokens,
+                temperature: model.temperature,
+                top_p: model.top_p,
+            };
+            match provider.complete(&req).await {
                 Ok(response) => {
-                    info!("Successfully generated response using {} ({})", model.name, model.provider);
-                    return Ok(AiResponse {
-                        content: response,
-                        model_used: model.name.to_string(),
-                        provider: model.provider.to_string(),
-                    });
+                    info!("Successfully generated response using {} ({})", response.model_used, response.provider);
+                    return Ok(response);
                 }
                 Err(e) => {
-                    warn!("Failed to use model {} ({}): {}", model.name, model.provider, e);
+                    warn!("Failed to use model {}: {}", model.name, e);
                     last_error = Some(e);
                 }
             }
@@ -98,60 +302,28 @@ impl AiService {
         Err(last_error.unwrap_or_else(|| anyhow!("All AI models failed")))
     }
 
-    async fn try_model(&self, model: &ModelConfig, messages: &[ChatMessage]) -> Result<String> {
-        let payload = json!({
-            "model": model.name,
-            "messages": messages,
-            "max_tokens": 4000,
-            "temperature": 0.7,
-            "top_p": 0.9,
-            "stream": false
-        });
-
-        let response = self
-            .client
-            .post(model.endpoint)
-            .header("Authorization", format!("Bearer {}", self.openrouter_key))
-            .header("Content-Type", "application/json")
-            .header("HTTP-Referer", "https://blabout.com")
-            .header("X-Title", "Blabout AI Workspace")
-            .json(&payload)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("API error: {}", error_text));
-        }
-
-        let json: Value = response.json().await?;
-        
-        let content = json
-            .get("choices")
-            .and_then(|choices| choices.get(0))
-            .and_then(|choice| choice.get("message"))
-            .and_then(|message| message.get("content"))
-            .and_then(|content| content.as_str())
-            .ok_or_else(|| anyhow!("Invalid response format"))?;
-
-        Ok(content.to_string())
-    }
+    /// Runs the planner/coder/reviewer pipeline. When `on_update` is given, a clone of every
+    /// node is published to it as soon as its status changes, so a caller can stream live
+    /// progress (e.g. over a WebSocket) instead of waiting for the full `Vec<WorkflowNode>`.
+    pub async fn process_workflow(
+        &self,
+        user_message: &str,
+        on_update: Option<&broadcast::Sender<WorkflowNode>>,
+    ) -> Result<Vec<WorkflowNode>> {
+        let publish = |node: &WorkflowNode| {
+            if let Some(tx) = on_update {
+                let _ = tx.send(node.clone());
+            }
+        };
 
-    pub async fn process_workflow(&self, user_message: &str) -> Result<Vec<WorkflowNode>> {
         // Create planning agent
         let planner_messages = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: "You are an AI planning agent. Break down the user's request into actionable steps for a coding workflow. Respond with a JSON array of steps, each with 'title', 'description', and 'agent_type' fields.".to_string(),
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: user_message.to_string(),
-            },
+            ChatMessage::new("system".to_string(), "You are an AI planning agent. Break down the user's request into actionable steps for a coding workflow. Respond with a JSON array of steps, each with 'title', 'description', and 'agent_type' fields.".to_string()),
+            ChatMessage::new("user".to_string(), user_message.to_string()),
         ];
 
         let planner_response = self.generate_response(planner_messages).await?;
-        
+
         // For demo purposes, create workflow nodes
         let mut nodes = vec![
             WorkflowNode {
@@ -179,45 +351,185 @@ impl AiService {
                 output: None,
             },
         ];
+        nodes.iter().for_each(publish);
 
         // Process coding agent
         let coder_messages = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: "You are a senior software engineer. Based on the planning output, write clean, production-ready code. Focus on best practices, error handling, and maintainability.".to_string(),
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: format!("Plan: {}\n\nOriginal request: {}", planner_response.content, user_message),
-            },
+            ChatMessage::new("system".to_string(), "You are a senior software engineer. Based on the planning output, write clean, production-ready code. Focus on best practices, error handling, and maintainability.".to_string()),
+            ChatMessage::new("user".to_string(), format!("Plan: {}\n\nOriginal request: {}", planner_response.content, user_message)),
         ];
 
         if let Ok(coder_response) = self.generate_response(coder_messages).await {
             nodes[1].status = "completed".to_string();
             nodes[1].output = Some(coder_response.content.clone());
             nodes[2].status = "processing".to_string();
+            publish(&nodes[1]);
+            publish(&nodes[2]);
 
             // Process reviewer agent
             let reviewer_messages = vec![
-                ChatMessage {
-                    role: "system".to_string(),
-                    content: "You are a code review expert. Analyze the provided code for improvements, security issues, and optimization opportunities. Provide constructive feedback.".to_string(),
-                },
-                ChatMessage {
-                    role: "user".to_string(),
-                    content: format!("Code to review: {}", coder_response.content),
-                },
+                ChatMessage::new("system".to_string(), "You are a code review expert. Analyze the provided code for improvements, security issues, and optimization opportunities. Provide constructive feedback.".to_string()),
+                ChatMessage::new("user".to_string(), format!("Code to review: {}", coder_response.content)),
             ];
 
             if let Ok(reviewer_response) = self.generate_response(reviewer_messages).await {
                 nodes[2].status = "completed".to_string();
                 nodes[2].output = Some(reviewer_response.content);
+                publish(&nodes[2]);
             }
         }
 
         Ok(nodes)
     }
 
+    /// Streams a chat completion, forwarding incremental content chunks as they arrive so
+    /// callers aren't stuck waiting for a full buffered response before showing anything.
+    /// Falls back to the next `ModelConfig` only if a model errors before its first token.
+    pub async fn generate_response_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<mpsc::Receiver<Result<String>>> {
+        let mut last_error = None;
+
+        for model in &self.models {
+            let provider = match self.provider_for(model) {
+                Ok(p) => p,
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            let req = CompletionRequest {
+                model: model.name.clone(),
+                messages: messages.clone(),
+                max_tokens: model.max_tokens,
+                temperature: model.temperature,
+                top_p: model.top_p,
+            };
+            match provider.stream(&req).await {
+                Ok(rx) => {
+                    info!("Streaming response using {} ({})", model.name, model.provider);
+                    return Ok(rx);
+                }
+                Err(e) => {
+                    warn!("Failed to start stream with {} ({}): {}", model.name, model.provider, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("All AI models failed")))
+    }
+
+    /// Runs an agent loop: sends `messages` plus the `tools` schema, dispatches any
+    /// `tool_calls` the model returns to their registered handlers, appends the results
+    /// as `role: "tool"` messages, and re-sends until the model replies normally or
+    /// `max_steps` tool-calling rounds have run.
+    ///
+    /// Tools with `requires_approval = true` are only dispatched if `approve` is given and
+    /// returns `true` for that call; otherwise the handler is skipped and a `tool` message
+    /// reporting the denial is appended so the model can react (e.g. ask the user directly).
+    pub async fn generate_with_tools(
+        &self,
+        mut messages: Vec<ChatMessage>,
+        tools: &[Tool],
+        max_steps: usize,
+        approve: Option<&(dyn Fn(&str, &Value) -> bool + Send + Sync)>,
+    ) -> Result<AiResponse> {
+        let schemas: Vec<Value> = tools.iter().map(Tool::to_openai_schema).collect();
+
+        for _ in 0..max_steps {
+            let mut last_error = None;
+            let mut outcome = None;
+
+            for model in &self.models {
+                let provider = match self.provider_for(model) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        last_error = Some(e);
+                        continue;
+                    }
+                };
+                let req = CompletionRequest {
+                    model: model.name.clone(),
+                    messages: messages.clone(),
+                    max_tokens: model.max_tokens,
+                    temperature: model.temperature,
+                    top_p: model.top_p,
+                };
+                match provider.complete_with_tools(&req, &schemas).await {
+                    Ok(message) => {
+                        outcome = Some((model.clone(), message));
+                        break;
+                    }
+                    Err(e) => {
+                        warn!("Failed to use model {} ({}): {}", model.name, model.provider, e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            let (model, message) = match outcome {
+                Some(o) => o,
+                None => return Err(last_error.unwrap_or_else(|| anyhow!("All AI models failed"))),
+            };
+
+            let tool_calls = message.get("tool_calls").and_then(|tc| tc.as_array()).cloned();
+
+            match tool_calls {
+                Some(tool_calls) if !tool_calls.is_empty() => {
+                    messages.push(ChatMessage {
+                        role: "assistant".to_string(),
+                        content: message.get("content").and_then(|c| c.as_str()).unwrap_or_default().to_string(),
+                        tool_call_id: None,
+                        tool_calls: Some(tool_calls.clone()),
+                    });
+
+                    for call in tool_calls {
+                        let call_id = call.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                        let name = call
+                            .get("function")
+                            .and_then(|f| f.get("name"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or_default();
+                        let args_str = call
+                            .get("function")
+                            .and_then(|f| f.get("arguments"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("{}");
+                        let args: Value = serde_json::from_str(args_str).unwrap_or(Value::Null);
+
+                        let result = match tools.iter().find(|t| t.name == name) {
+                            Some(tool) if tool.requires_approval && !approve.is_some_and(|a| a(&tool.name, &args)) => {
+                                warn!("Denied unapproved call to side-effecting tool {}", tool.name);
+                                json!({"error": format!("tool '{}' requires approval and was not approved", tool.name)})
+                            }
+                            Some(tool) => (tool.handler)(args).await.unwrap_or_else(|e| json!({"error": e.to_string()})),
+                            None => json!({"error": format!("unknown tool: {}", name)}),
+                        };
+
+                        messages.push(ChatMessage {
+                            role: "tool".to_string(),
+                            content: result.to_string(),
+                            tool_call_id: Some(call_id),
+                            tool_calls: None,
+                        });
+                    }
+                }
+                _ => {
+                    let content = message.get("content").and_then(|c| c.as_str()).unwrap_or_default().to_string();
+                    return Ok(AiResponse {
+                        content,
+                        model_used: model.name.to_string(),
+                        provider: model.provider.to_string(),
+                    });
+                }
+            }
+        }
+
+        Err(anyhow!("tool-calling loop exceeded max_steps ({})", max_steps))
+    }
+
     pub async fn list_available_models(&self) -> Result<Vec<Value>> {
         let response = self
             .client
@@ -241,7 +553,7 @@ impl AiService {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct WorkflowNode {
     pub id: String,
     pub node_type: String,
@@ -1,15 +1,111 @@
+use blake2::digest::{FixedOutput, KeyInit, Mac, Update};
+use blake2::Blake2bMac;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::XChaCha20;
 use chrono::{DateTime, Duration, Utc};
 use ed25519_dalek::{Signature, SigningKey, VerifyingKey, Signer, Verifier};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use base64::Engine;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct PasetoClaims {
     pub sub: String,
     pub email: Option<String>,
     pub name: Option<String>,
     pub iat: String,
     pub exp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Authorization scopes granted to this token (e.g. `workspaces:read`), checked by
+    /// handler guards rather than by `validate_registered_claims`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<String>>,
+}
+
+/// Errors produced while verifying a PASETO token, distinct enough that callers can
+/// map them to the right auth response (e.g. "expired" vs "not yet valid").
+#[derive(Debug, Error)]
+pub enum PasetoError {
+    #[error("invalid token version/purpose")]
+    InvalidVersion,
+    #[error("invalid token format")]
+    InvalidFormat,
+    #[error("base64 decode error: {0}")]
+    Base64(String),
+    #[error("signing key not available")]
+    SigningKeyUnavailable,
+    #[error("signature verify failed: {0}")]
+    SignatureInvalid(String),
+    #[error("authentication tag mismatch")]
+    TagMismatch,
+    #[error("footer mismatch")]
+    FooterMismatch,
+    #[error("claims serialization error: {0}")]
+    Serde(String),
+    #[error("token expired")]
+    Expired,
+    #[error("token not yet valid")]
+    NotYetValid,
+    #[error("audience mismatch")]
+    AudienceMismatch,
+    #[error("issuer mismatch")]
+    IssuerMismatch,
+}
+
+/// Options controlling registered-claims validation during verification.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationOptions {
+    /// Clock skew tolerance applied to `exp`/`nbf` checks.
+    pub leeway: Duration,
+    pub expected_aud: Option<String>,
+    pub expected_iss: Option<String>,
+}
+
+/// Checks `exp`/`nbf`/`iat`/`aud`/`iss` against `opts`, applying `opts.leeway` as clock skew tolerance.
+fn validate_registered_claims(claims: &PasetoClaims, opts: &ValidationOptions) -> Result<(), PasetoError> {
+    let now = Utc::now();
+
+    let exp: DateTime<Utc> = claims.exp.parse().map_err(|_| PasetoError::InvalidFormat)?;
+    if now > exp + opts.leeway {
+        return Err(PasetoError::Expired);
+    }
+
+    if let Some(nbf) = &claims.nbf {
+        let nbf: DateTime<Utc> = nbf.parse().map_err(|_| PasetoError::InvalidFormat)?;
+        if now < nbf - opts.leeway {
+            return Err(PasetoError::NotYetValid);
+        }
+    }
+
+    // `iat` has no expiry semantics of its own, but a timestamp from the future would mean the
+    // token was minted by a clock we don't trust, so treat it like an implicit `nbf`.
+    let iat: DateTime<Utc> = claims.iat.parse().map_err(|_| PasetoError::InvalidFormat)?;
+    if now < iat - opts.leeway {
+        return Err(PasetoError::NotYetValid);
+    }
+
+    if let Some(expected_aud) = &opts.expected_aud {
+        if claims.aud.as_deref() != Some(expected_aud.as_str()) {
+            return Err(PasetoError::AudienceMismatch);
+        }
+    }
+
+    if let Some(expected_iss) = &opts.expected_iss {
+        if claims.iss.as_deref() != Some(expected_iss.as_str()) {
+            return Err(PasetoError::IssuerMismatch);
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -18,6 +114,21 @@ pub struct PasetoKeys {
     pub signing_key: Option<SigningKey>,
 }
 
+/// Symmetric key for v4.local (encrypted) tokens.
+#[derive(Clone)]
+pub struct PasetoLocalKey(pub [u8; 32]);
+
+impl PasetoLocalKey {
+    pub fn from_base64(secret_b64: &str) -> Result<Self, String> {
+        let bytes = b64url_decode_nopad(secret_b64)?;
+        let key: [u8; 32] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| "local key must be 32 bytes".to_string())?;
+        Ok(Self(key))
+    }
+}
+
 fn pae(pieces: &[&[u8]]) -> Vec<u8> {
     // PASETO Pre-Authentication Encoding
     // le64 of number of pieces, then for each piece le64(len) || piece
@@ -41,6 +152,37 @@ fn b64url_decode_nopad(s: &str) -> Result<Vec<u8>, String> {
         .map_err(|e| format!("base64 decode error: {}", e))
 }
 
+/// Derives the v4.local encryption key `Ek`, nonce `n2`, and auth key `Ak` from the
+/// secret and per-token random nonce `n`, per the PASETO v4.local key-splitting algorithm.
+fn split_local_keys(secret: &[u8; 32], n: &[u8; 32]) -> ([u8; 32], [u8; 24], [u8; 32]) {
+    let mut enc_mac = Blake2bMac::<blake2::digest::consts::U56>::new_from_slice(secret)
+        .expect("blake2b accepts a 32-byte key");
+    enc_mac.update(b"paseto-encryption-key");
+    enc_mac.update(n);
+    let enc_blob = enc_mac.finalize_fixed();
+
+    let mut ek = [0u8; 32];
+    let mut n2 = [0u8; 24];
+    ek.copy_from_slice(&enc_blob[0..32]);
+    n2.copy_from_slice(&enc_blob[32..56]);
+
+    let mut auth_mac = Blake2bMac::<blake2::digest::consts::U32>::new_from_slice(secret)
+        .expect("blake2b accepts a 32-byte key");
+    auth_mac.update(b"paseto-auth-key-for-aead");
+    auth_mac.update(n);
+    let ak: [u8; 32] = auth_mac.finalize_fixed().into();
+
+    (ek, n2, ak)
+}
+
+fn local_mac(ak: &[u8; 32], header: &[u8], n: &[u8; 32], c: &[u8], footer: &[u8], implicit: &[u8]) -> [u8; 32] {
+    let pae_bytes = pae(&[header, n, c, footer, implicit]);
+    let mut mac = Blake2bMac::<blake2::digest::consts::U32>::new_from_slice(ak)
+        .expect("blake2b accepts a 32-byte key");
+    mac.update(&pae_bytes);
+    mac.finalize_fixed().into()
+}
+
 impl PasetoKeys {
     pub fn from_base64(public_b64: &str, secret_b64: Option<&str>) -> Result<Self, String> {
         let pub_bytes = b64url_decode_nopad(public_b64)?;
@@ -66,9 +208,15 @@ impl PasetoKeys {
     }
 }
 
-pub fn issue_v4_public(keys: &PasetoKeys, claims: &PasetoClaims) -> Result<String, String> {
+pub fn issue_v4_public(
+    keys: &PasetoKeys,
+    claims: &PasetoClaims,
+    footer: Option<&[u8]>,
+    implicit: Option<&[u8]>,
+) -> Result<String, String> {
     let header = b"v4.public.";
-    let footer: &[u8] = b"";
+    let footer = footer.unwrap_or(b"");
+    let implicit = implicit.unwrap_or(b"");
     let payload = serde_json::to_vec(claims).map_err(|e| e.to_string())?;
 
     let signing_key = keys
@@ -76,56 +224,404 @@ pub fn issue_v4_public(keys: &PasetoKeys, claims: &PasetoClaims) -> Result<Strin
         .as_ref()
         .ok_or_else(|| "signing key not available".to_string())?;
 
-    let pae_bytes = pae(&[header, &payload, footer]);
+    let pae_bytes = pae(&[header, &payload, footer, implicit]);
     let sig: Signature = signing_key.sign(&pae_bytes);
 
-    let token = format!(
+    let mut token = format!(
         "v4.public.{}.{}",
         b64url_nopad(&payload),
         b64url_nopad(&sig.to_bytes())
     );
+    if !footer.is_empty() {
+        token.push('.');
+        token.push_str(&b64url_nopad(footer));
+    }
     Ok(token)
 }
 
-pub fn verify_v4_public(keys: &PasetoKeys, token: &str) -> Result<PasetoClaims, String> {
+pub fn verify_v4_public(
+    keys: &PasetoKeys,
+    token: &str,
+    expected_footer: Option<&[u8]>,
+    implicit: Option<&[u8]>,
+    opts: &ValidationOptions,
+) -> Result<PasetoClaims, PasetoError> {
     if !token.starts_with("v4.public.") {
-        return Err("invalid token version/purpose".to_string());
+        return Err(PasetoError::InvalidVersion);
     }
     let rest = &token[10..]; // after 'v4.public.'
     let parts: Vec<&str> = rest.split('.').collect();
-    if parts.len() != 2 {
-        return Err("invalid token format".to_string());
+    if parts.len() != 2 && parts.len() != 3 {
+        return Err(PasetoError::InvalidFormat);
+    }
+    let payload = b64url_decode_nopad(parts[0]).map_err(PasetoError::Base64)?;
+    let sig = b64url_decode_nopad(parts[1]).map_err(PasetoError::Base64)?;
+    if sig.len() != 64 { return Err(PasetoError::InvalidFormat); }
+
+    let footer = match parts.get(2) {
+        Some(f) => b64url_decode_nopad(f).map_err(PasetoError::Base64)?,
+        None => Vec::new(),
+    };
+    if let Some(expected) = expected_footer {
+        if !constant_time_eq(expected, &footer) {
+            return Err(PasetoError::FooterMismatch);
+        }
     }
-    let payload = b64url_decode_nopad(parts[0])?;
-    let sig = b64url_decode_nopad(parts[1])?;
-    if sig.len() != 64 { return Err("invalid signature length".to_string()); }
 
     let header = b"v4.public.";
-    let footer: &[u8] = b"";
-    let pae_bytes = pae(&[header, &payload, footer]);
+    let implicit = implicit.unwrap_or(b"");
+    let pae_bytes = pae(&[header, &payload, &footer, implicit]);
 
     let signature = Signature::from_bytes(
-        sig.as_slice().try_into().map_err(|_| "invalid signature bytes".to_string())?
+        sig.as_slice().try_into().map_err(|_| PasetoError::InvalidFormat)?
     );
     keys.verifying_key.verify(&pae_bytes, &signature)
-        .map_err(|e| format!("signature verify failed: {}", e))?;
+        .map_err(|e| PasetoError::SignatureInvalid(e.to_string()))?;
 
-    let claims: PasetoClaims = serde_json::from_slice(&payload).map_err(|e| e.to_string())?;
-    // Basic time validation
-    let now = Utc::now();
-    let exp: DateTime<Utc> = claims.exp.parse().map_err(|_| "exp parse".to_string())?;
-    if now > exp { return Err("token expired".to_string()); }
+    let claims: PasetoClaims = serde_json::from_slice(&payload).map_err(|e| PasetoError::Serde(e.to_string()))?;
+    validate_registered_claims(&claims, opts)?;
+    Ok(claims)
+}
+
+/// Issues a v4.local (encrypted) PASETO: the claims are not readable without the secret key,
+/// which is required for session tokens whose contents should not leak.
+pub fn issue_v4_local(
+    key: &PasetoLocalKey,
+    claims: &PasetoClaims,
+    footer: Option<&[u8]>,
+    implicit: Option<&[u8]>,
+) -> Result<String, String> {
+    let header = b"v4.local.";
+    let footer = footer.unwrap_or(b"");
+    let implicit = implicit.unwrap_or(b"");
+    let payload = serde_json::to_vec(claims).map_err(|e| e.to_string())?;
+
+    let mut n = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut n);
+
+    let (ek, n2, ak) = split_local_keys(&key.0, &n);
+
+    let mut c = payload;
+    let mut cipher = XChaCha20::new((&ek).into(), (&n2).into());
+    cipher.apply_keystream(&mut c);
+
+    let t = local_mac(&ak, header, &n, &c, footer, implicit);
+
+    let mut body = Vec::with_capacity(n.len() + c.len() + t.len());
+    body.extend_from_slice(&n);
+    body.extend_from_slice(&c);
+    body.extend_from_slice(&t);
+
+    let mut token = format!("v4.local.{}", b64url_nopad(&body));
+    if !footer.is_empty() {
+        token.push('.');
+        token.push_str(&b64url_nopad(footer));
+    }
+    Ok(token)
+}
+
+pub fn verify_v4_local(
+    key: &PasetoLocalKey,
+    token: &str,
+    expected_footer: Option<&[u8]>,
+    implicit: Option<&[u8]>,
+    opts: &ValidationOptions,
+) -> Result<PasetoClaims, PasetoError> {
+    if !token.starts_with("v4.local.") {
+        return Err(PasetoError::InvalidVersion);
+    }
+    let header = b"v4.local.";
+    let implicit = implicit.unwrap_or(b"");
+
+    let rest = &token[9..]; // after 'v4.local.'
+    let parts: Vec<&str> = rest.split('.').collect();
+    if parts.len() != 1 && parts.len() != 2 {
+        return Err(PasetoError::InvalidFormat);
+    }
+    let body = b64url_decode_nopad(parts[0]).map_err(PasetoError::Base64)?;
+    if body.len() < 32 + 32 {
+        return Err(PasetoError::InvalidFormat);
+    }
+
+    let footer = match parts.get(1) {
+        Some(f) => b64url_decode_nopad(f).map_err(PasetoError::Base64)?,
+        None => Vec::new(),
+    };
+    if let Some(expected) = expected_footer {
+        if !constant_time_eq(expected, &footer) {
+            return Err(PasetoError::FooterMismatch);
+        }
+    }
+
+    let n: [u8; 32] = body[0..32].try_into().unwrap();
+    let t: [u8; 32] = body[body.len() - 32..].try_into().unwrap();
+    let c = &body[32..body.len() - 32];
+
+    let (ek, n2, ak) = split_local_keys(&key.0, &n);
+
+    let expected_t = local_mac(&ak, header, &n, c, &footer, implicit);
+    if !constant_time_eq(&expected_t, &t) {
+        return Err(PasetoError::TagMismatch);
+    }
+
+    let mut payload = c.to_vec();
+    let mut cipher = XChaCha20::new((&ek).into(), (&n2).into());
+    cipher.apply_keystream(&mut payload);
+
+    let claims: PasetoClaims = serde_json::from_slice(&payload).map_err(|e| PasetoError::Serde(e.to_string()))?;
+    validate_registered_claims(&claims, opts)?;
     Ok(claims)
 }
 
-pub fn build_default_claims(sub: String, email: Option<String>, name: Option<String>) -> PasetoClaims {
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() { return false; }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
+/// Mints an opaque 32-byte refresh token and the SHA-256 hash that should be stored in
+/// `sessions.token_hash`. Only the hash is persisted; the raw token is returned once to the
+/// caller and can't be recovered from the stored row.
+pub fn generate_refresh_token() -> (String, Vec<u8>) {
+    let mut raw = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut raw);
+    let token = b64url_nopad(&raw);
+    let hash = Sha256::digest(raw).to_vec();
+    (token, hash)
+}
+
+/// Hashes a client-presented refresh token the same way `generate_refresh_token` does, so it
+/// can be looked up by `sessions.token_hash`.
+pub fn hash_refresh_token(token: &str) -> Result<Vec<u8>, String> {
+    let raw = b64url_decode_nopad(token)?;
+    Ok(Sha256::digest(&raw).to_vec())
+}
+
+pub fn build_default_claims(
+    sub: String,
+    email: Option<String>,
+    name: Option<String>,
+    scope: Vec<String>,
+) -> PasetoClaims {
     let now = Utc::now();
     let exp = now + Duration::hours(1);
+    let mut jti_bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut jti_bytes);
     PasetoClaims {
         sub,
         email,
         name,
         iat: now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
         exp: exp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        nbf: Some(now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        aud: None,
+        iss: None,
+        jti: Some(b64url_nopad(&jti_bytes)),
+        scope: if scope.is_empty() { None } else { Some(scope) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_claims() -> PasetoClaims {
+        build_default_claims(
+            "user-123".to_string(),
+            Some("user@example.com".to_string()),
+            Some("Test User".to_string()),
+            vec!["workspaces:read".to_string()],
+        )
+    }
+
+    fn test_local_key() -> PasetoLocalKey {
+        PasetoLocalKey([0x42; 32])
     }
-}
\ No newline at end of file
+
+    fn test_keys() -> PasetoKeys {
+        let signing_key = SigningKey::from_bytes(&[0x07; 32]);
+        let verifying_key = signing_key.verifying_key();
+        PasetoKeys { verifying_key, signing_key: Some(signing_key) }
+    }
+
+    #[test]
+    fn v4_local_round_trip() {
+        let key = test_local_key();
+        let claims = test_claims();
+        let token = issue_v4_local(&key, &claims, None, None).unwrap();
+        assert!(token.starts_with("v4.local."));
+
+        let verified = verify_v4_local(&key, &token, None, None, &ValidationOptions::default()).unwrap();
+        assert_eq!(verified.sub, claims.sub);
+        assert_eq!(verified.exp, claims.exp);
+    }
+
+    #[test]
+    fn v4_local_tampered_ciphertext_fails_tag_check() {
+        let key = test_local_key();
+        let token = issue_v4_local(&key, &test_claims(), None, None).unwrap();
+
+        let (header, body) = token.split_at("v4.local.".len());
+        let mut raw = b64url_decode_nopad(body).unwrap();
+        let mid = raw.len() / 2;
+        raw[mid] ^= 0xFF;
+        let tampered = format!("{header}{}", b64url_nopad(&raw));
+
+        let err = verify_v4_local(&key, &tampered, None, None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::TagMismatch));
+    }
+
+    #[test]
+    fn v4_local_wrong_key_fails_tag_check() {
+        let claims = test_claims();
+        let token = issue_v4_local(&test_local_key(), &claims, None, None).unwrap();
+        let wrong_key = PasetoLocalKey([0x99; 32]);
+        let err = verify_v4_local(&wrong_key, &token, None, None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::TagMismatch));
+    }
+
+    #[test]
+    fn v4_public_round_trip() {
+        let keys = test_keys();
+        let claims = test_claims();
+        let token = issue_v4_public(&keys, &claims, None, None).unwrap();
+        assert!(token.starts_with("v4.public."));
+
+        let verified = verify_v4_public(&keys, &token, None, None, &ValidationOptions::default()).unwrap();
+        assert_eq!(verified.sub, claims.sub);
+    }
+
+    #[test]
+    fn v4_public_tampered_payload_fails_signature_check() {
+        let keys = test_keys();
+        let token = issue_v4_public(&keys, &test_claims(), None, None).unwrap();
+
+        let parts: Vec<&str> = token["v4.public.".len()..].split('.').collect();
+        let mut payload = b64url_decode_nopad(parts[0]).unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 0xFF;
+        let tampered = format!("v4.public.{}.{}", b64url_nopad(&payload), parts[1]);
+
+        let err = verify_v4_public(&keys, &tampered, None, None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::SignatureInvalid(_)));
+    }
+
+    #[test]
+    fn v4_public_wrong_verifying_key_fails_signature_check() {
+        let claims = test_claims();
+        let token = issue_v4_public(&test_keys(), &claims, None, None).unwrap();
+
+        let other_signing_key = SigningKey::from_bytes(&[0x13; 32]);
+        let other_keys = PasetoKeys {
+            verifying_key: other_signing_key.verifying_key(),
+            signing_key: None,
+        };
+        let err = verify_v4_public(&other_keys, &token, None, None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::SignatureInvalid(_)));
+    }
+
+    #[test]
+    fn v4_local_footer_mismatch_is_rejected() {
+        let key = test_local_key();
+        let token = issue_v4_local(&key, &test_claims(), Some(b"kid:1"), None).unwrap();
+        let err = verify_v4_local(&key, &token, Some(b"kid:2"), None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::FooterMismatch));
+    }
+
+    #[test]
+    fn v4_public_footer_mismatch_is_rejected() {
+        let keys = test_keys();
+        let token = issue_v4_public(&keys, &test_claims(), Some(b"kid:1"), None).unwrap();
+        let err = verify_v4_public(&keys, &token, Some(b"kid:2"), None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::FooterMismatch));
+    }
+
+    #[test]
+    fn v4_local_wrong_implicit_assertion_fails_tag_check() {
+        let key = test_local_key();
+        let token = issue_v4_local(&key, &test_claims(), None, Some(b"request-id:1")).unwrap();
+        let err = verify_v4_local(&key, &token, None, Some(b"request-id:2"), &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::TagMismatch));
+    }
+
+    #[test]
+    fn v4_public_wrong_implicit_assertion_fails_signature_check() {
+        let keys = test_keys();
+        let token = issue_v4_public(&keys, &test_claims(), None, Some(b"request-id:1")).unwrap();
+        let err = verify_v4_public(&keys, &token, None, Some(b"request-id:2"), &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::SignatureInvalid(_)));
+    }
+
+    fn claims_with(mutate: impl FnOnce(&mut PasetoClaims)) -> PasetoClaims {
+        let mut claims = test_claims();
+        mutate(&mut claims);
+        claims
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let key = test_local_key();
+        let claims = claims_with(|c| {
+            c.exp = (Utc::now() - Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        });
+        let token = issue_v4_local(&key, &claims, None, None).unwrap();
+        let err = verify_v4_local(&key, &token, None, None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::Expired));
+    }
+
+    #[test]
+    fn expired_token_within_leeway_is_accepted() {
+        let key = test_local_key();
+        let claims = claims_with(|c| {
+            c.exp = (Utc::now() - Duration::seconds(5)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        });
+        let token = issue_v4_local(&key, &claims, None, None).unwrap();
+        let opts = ValidationOptions { leeway: Duration::seconds(30), ..Default::default() };
+        assert!(verify_v4_local(&key, &token, None, None, &opts).is_ok());
+    }
+
+    #[test]
+    fn future_nbf_is_rejected() {
+        let key = test_local_key();
+        let claims = claims_with(|c| {
+            c.nbf = Some((Utc::now() + Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+        });
+        let token = issue_v4_local(&key, &claims, None, None).unwrap();
+        let err = verify_v4_local(&key, &token, None, None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::NotYetValid));
+    }
+
+    #[test]
+    fn future_iat_is_rejected() {
+        let key = test_local_key();
+        let claims = claims_with(|c| {
+            c.iat = (Utc::now() + Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        });
+        let token = issue_v4_local(&key, &claims, None, None).unwrap();
+        let err = verify_v4_local(&key, &token, None, None, &ValidationOptions::default()).unwrap_err();
+        assert!(matches!(err, PasetoError::NotYetValid));
+    }
+
+    #[test]
+    fn aud_mismatch_is_rejected() {
+        let key = test_local_key();
+        let claims = claims_with(|c| c.aud = Some("api.example.com".to_string()));
+        let token = issue_v4_local(&key, &claims, None, None).unwrap();
+        let opts = ValidationOptions { expected_aud: Some("other.example.com".to_string()), ..Default::default() };
+        let err = verify_v4_local(&key, &token, None, None, &opts).unwrap_err();
+        assert!(matches!(err, PasetoError::AudienceMismatch));
+    }
+
+    #[test]
+    fn iss_mismatch_is_rejected() {
+        let key = test_local_key();
+        let claims = claims_with(|c| c.iss = Some("blabout".to_string()));
+        let token = issue_v4_local(&key, &claims, None, None).unwrap();
+        let opts = ValidationOptions { expected_iss: Some("other".to_string()), ..Default::default() };
+        let err = verify_v4_local(&key, &token, None, None, &opts).unwrap_err();
+        assert!(matches!(err, PasetoError::IssuerMismatch));
+    }
+}
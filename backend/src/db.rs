@@ -1,14 +1,15 @@
 use anyhow::Result;
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use tokio_postgres::{NoTls, Row};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 pub type DbPool = Pool<PostgresConnectionManager<NoTls>>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
@@ -17,7 +18,7 @@ pub struct User {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Workspace {
     pub id: Uuid,
     pub name: String,
@@ -26,6 +27,33 @@ pub struct Workspace {
     pub updated_at: DateTime<Utc>,
 }
 
+/// A refresh-token session. The token itself is never stored, only the SHA-256 hash of the
+/// opaque value handed to the client, so a leaked database row can't be replayed directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    #[serde(skip_serializing)]
+    pub token_hash: Vec<u8>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A stored binary asset (e.g. a normalized workspace avatar). `owner_id` is whatever entity
+/// the asset belongs to (a workspace, a user, ...); `kind` distinguishes the asset's purpose
+/// (e.g. `"workspace_avatar"`) since one owner can end up with more than one asset over time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Asset {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub kind: String,
+    #[serde(skip_serializing)]
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowExecution {
     pub id: Uuid,
@@ -88,10 +116,38 @@ pub async fn init_schema(pool: &DbPool) -> Result<()> {
         &[],
     ).await?;
 
+    // Create sessions table (refresh tokens for PASETO auth)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+            user_id UUID NOT NULL REFERENCES users(id),
+            token_hash BYTEA NOT NULL,
+            expires_at TIMESTAMPTZ NOT NULL,
+            revoked BOOLEAN NOT NULL DEFAULT FALSE,
+            created_at TIMESTAMPTZ DEFAULT NOW()
+        )",
+        &[],
+    ).await?;
+
+    // Create assets table (normalized uploads: workspace avatars, etc.)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS assets (
+            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+            owner_id UUID NOT NULL,
+            kind VARCHAR NOT NULL,
+            bytes BYTEA NOT NULL,
+            content_type VARCHAR NOT NULL,
+            created_at TIMESTAMPTZ DEFAULT NOW()
+        )",
+        &[],
+    ).await?;
+
     // Create indexes
     conn.execute("CREATE INDEX IF NOT EXISTS idx_workspaces_user_id ON workspaces(user_id)", &[]).await?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_assets_owner_id ON assets(owner_id)", &[]).await?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_workflow_executions_workspace_id ON workflow_executions(workspace_id)", &[]).await?;
-    
+    conn.execute("CREATE UNIQUE INDEX IF NOT EXISTS idx_sessions_token_hash ON sessions(token_hash)", &[]).await?;
+
     Ok(())
 }
 
@@ -110,6 +166,19 @@ impl User {
         }
     }
 
+    pub async fn find_by_id(pool: &DbPool, id: Uuid) -> Result<Option<User>> {
+        let conn = pool.get().await?;
+        let row = conn.query_opt(
+            "SELECT id, email, name, kinde_id as auth_provider_id, created_at FROM users WHERE id = $1",
+            &[&id],
+        ).await?;
+
+        match row {
+            Some(row) => Ok(Some(User::from_row(row))),
+            None => Ok(None),
+        }
+    }
+
     pub async fn create(pool: &DbPool, email: String, name: String, auth_provider_id: String) -> Result<User> {
         let conn = pool.get().await?;
         let row = conn.query_one(
@@ -133,6 +202,19 @@ impl User {
 }
 
 impl Workspace {
+    pub async fn find_by_id(pool: &DbPool, id: Uuid) -> Result<Option<Workspace>> {
+        let conn = pool.get().await?;
+        let row = conn.query_opt(
+            "SELECT id, name, user_id, created_at, updated_at FROM workspaces WHERE id = $1",
+            &[&id],
+        ).await?;
+
+        match row {
+            Some(row) => Ok(Some(Workspace::from_row(row))),
+            None => Ok(None),
+        }
+    }
+
     pub async fn find_by_user_id(pool: &DbPool, user_id: Uuid) -> Result<Vec<Workspace>> {
         let conn = pool.get().await?;
         let rows = conn.query(
@@ -164,3 +246,147 @@ impl Workspace {
         }
     }
 }
+
+#[allow(dead_code)]
+impl Session {
+    /// Inserts a new session row for `user_id`, expiring `ttl` from now.
+    pub async fn create(pool: &DbPool, user_id: Uuid, token_hash: &[u8], ttl: Duration) -> Result<Session> {
+        let conn = pool.get().await?;
+        let expires_at = Utc::now() + ttl;
+        let row = conn.query_one(
+            "INSERT INTO sessions (user_id, token_hash, expires_at) VALUES ($1, $2, $3)
+             RETURNING id, user_id, token_hash, expires_at, revoked, created_at",
+            &[&user_id, &token_hash, &expires_at],
+        ).await?;
+
+        Ok(Session::from_row(row))
+    }
+
+    pub async fn find_by_token_hash(pool: &DbPool, token_hash: &[u8]) -> Result<Option<Session>> {
+        let conn = pool.get().await?;
+        let row = conn.query_opt(
+            "SELECT id, user_id, token_hash, expires_at, revoked, created_at FROM sessions WHERE token_hash = $1",
+            &[&token_hash],
+        ).await?;
+
+        match row {
+            Some(row) => Ok(Some(Session::from_row(row))),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn revoke(pool: &DbPool, id: Uuid) -> Result<()> {
+        let conn = pool.get().await?;
+        conn.execute("UPDATE sessions SET revoked = TRUE WHERE id = $1", &[&id]).await?;
+        Ok(())
+    }
+
+    fn from_row(row: Row) -> Self {
+        Session {
+            id: row.get("id"),
+            user_id: row.get("user_id"),
+            token_hash: row.get("token_hash"),
+            expires_at: row.get("expires_at"),
+            revoked: row.get("revoked"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Asset {
+    pub async fn create(
+        pool: &DbPool,
+        owner_id: Uuid,
+        kind: &str,
+        bytes: Vec<u8>,
+        content_type: &str,
+    ) -> Result<Asset> {
+        let conn = pool.get().await?;
+        let row = conn.query_one(
+            "INSERT INTO assets (owner_id, kind, bytes, content_type) VALUES ($1, $2, $3, $4)
+             RETURNING id, owner_id, kind, bytes, content_type, created_at",
+            &[&owner_id, &kind, &bytes, &content_type],
+        ).await?;
+
+        Ok(Asset::from_row(row))
+    }
+
+    pub async fn find(pool: &DbPool, id: Uuid) -> Result<Option<Asset>> {
+        let conn = pool.get().await?;
+        let row = conn.query_opt(
+            "SELECT id, owner_id, kind, bytes, content_type, created_at FROM assets WHERE id = $1",
+            &[&id],
+        ).await?;
+
+        match row {
+            Some(row) => Ok(Some(Asset::from_row(row))),
+            None => Ok(None),
+        }
+    }
+
+    fn from_row(row: Row) -> Self {
+        Asset {
+            id: row.get("id"),
+            owner_id: row.get("owner_id"),
+            kind: row.get("kind"),
+            bytes: row.get("bytes"),
+            content_type: row.get("content_type"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl WorkflowExecution {
+    pub async fn create(pool: &DbPool, workspace_id: Uuid, message: String) -> Result<WorkflowExecution> {
+        let conn = pool.get().await?;
+        let row = conn.query_one(
+            "INSERT INTO workflow_executions (workspace_id, message) VALUES ($1, $2)
+             RETURNING id, workspace_id, message, status, result, created_at, completed_at",
+            &[&workspace_id, &message],
+        ).await?;
+
+        Ok(WorkflowExecution::from_row(row))
+    }
+
+    /// Updates `status` (and `result`, when given). `completed_at` is stamped whenever the
+    /// new status is terminal (`completed` or `error`).
+    pub async fn update_status(pool: &DbPool, id: Uuid, status: &str, result: Option<String>) -> Result<()> {
+        let conn = pool.get().await?;
+        if matches!(status, "completed" | "error") {
+            conn.execute(
+                "UPDATE workflow_executions SET status = $1, result = COALESCE($2, result), completed_at = NOW() WHERE id = $3",
+                &[&status, &result, &id],
+            ).await?;
+        } else {
+            conn.execute(
+                "UPDATE workflow_executions SET status = $1, result = COALESCE($2, result) WHERE id = $3",
+                &[&status, &result, &id],
+            ).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn find_by_workspace(pool: &DbPool, workspace_id: Uuid) -> Result<Vec<WorkflowExecution>> {
+        let conn = pool.get().await?;
+        let rows = conn.query(
+            "SELECT id, workspace_id, message, status, result, created_at, completed_at FROM workflow_executions WHERE workspace_id = $1 ORDER BY created_at DESC",
+            &[&workspace_id],
+        ).await?;
+
+        Ok(rows.into_iter().map(WorkflowExecution::from_row).collect())
+    }
+
+    fn from_row(row: Row) -> Self {
+        WorkflowExecution {
+            id: row.get("id"),
+            workspace_id: row.get("workspace_id"),
+            message: row.get("message"),
+            status: row.get("status"),
+            result: row.get("result"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+        }
+    }
+}
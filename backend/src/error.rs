@@ -0,0 +1,93 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::auth_paseto::PasetoError;
+
+/// The JSON body every error response shares, so clients can branch on `status`
+/// without parsing the HTTP status line.
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub status: String,
+    pub message: String,
+}
+
+/// Unified error type for the HTTP layer. Handlers return `Result<_, ApiError>` and use
+/// `?` to propagate failures instead of collapsing everything into `INTERNAL_SERVER_ERROR`.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    InternalError(#[from] anyhow::Error),
+    #[error("missing credentials")]
+    MissingCredentials,
+    #[error("invalid or expired token")]
+    InvalidToken,
+    #[error("missing authorization token")]
+    MissingToken,
+    #[error("not authorized to perform this action")]
+    Unauthorized,
+    #[error("upstream request failed: {0}")]
+    UpstreamFailure(String),
+    #[error("resource not found")]
+    NotFound,
+    #[error("unsupported file format: {0}")]
+    UnsupportedMediaType(String),
+    #[error("upload exceeds the maximum allowed size")]
+    PayloadTooLarge,
+}
+
+impl ApiError {
+    fn status_and_label(&self) -> (StatusCode, &'static str) {
+        match self {
+            ApiError::InternalError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+            ApiError::MissingCredentials => (StatusCode::BAD_REQUEST, "missing_credentials"),
+            ApiError::InvalidToken => (StatusCode::UNAUTHORIZED, "invalid_token"),
+            ApiError::MissingToken => (StatusCode::UNAUTHORIZED, "missing_token"),
+            ApiError::Unauthorized => (StatusCode::FORBIDDEN, "unauthorized"),
+            ApiError::UpstreamFailure(_) => (StatusCode::BAD_GATEWAY, "upstream_failure"),
+            ApiError::NotFound => (StatusCode::NOT_FOUND, "not_found"),
+            ApiError::UnsupportedMediaType(_) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type"),
+            ApiError::PayloadTooLarge => (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large"),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if let ApiError::InternalError(err) = &self {
+            tracing::error!("internal error: {:#}", err);
+        }
+        let (code, label) = self.status_and_label();
+        let body = ErrorBody {
+            status: label.to_string(),
+            message: self.to_string(),
+        };
+        (code, Json(body)).into_response()
+    }
+}
+
+impl From<tokio_postgres::Error> for ApiError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        ApiError::InternalError(err.into())
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        ApiError::UpstreamFailure(err.to_string())
+    }
+}
+
+impl From<PasetoError> for ApiError {
+    fn from(_err: PasetoError) -> Self {
+        ApiError::InvalidToken
+    }
+}
+
+impl From<axum::extract::multipart::MultipartError> for ApiError {
+    fn from(err: axum::extract::multipart::MultipartError) -> Self {
+        ApiError::InternalError(err.into())
+    }
+}
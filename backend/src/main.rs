@@ -1,24 +1,35 @@
 use axum::{
-    extract::{State, Path, WebSocketUpgrade},
-    http::{HeaderMap, StatusCode},
+    extract::{Multipart, State, Path, WebSocketUpgrade},
+    http::{header, HeaderMap},
     response::{Json, IntoResponse},
     routing::{get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
 use tower_http::cors::CorsLayer;
-use tracing::info;
+use tracing::{info, warn};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
 mod db;
 mod ai_service;
 mod auth_paseto;
+mod error;
+mod oauth;
+mod providers;
 
 use db::{DbPool, User, Workspace};
 use auth_paseto::{issue_v4_public, build_default_claims, PasetoClaims};
 use ai_service::{AiService, WorkflowNode};
+use error::ApiError;
+use oauth::OAuthProvider;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateUser {
     pub email: String,
     pub name: String,
@@ -26,31 +37,113 @@ pub struct CreateUser {
 }
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateWorkspace {
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct WorkflowRequest {
     pub message: String,
     pub workspace_id: Uuid,
 }
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct WorkflowResponse {
+    pub execution_id: Uuid,
     pub nodes: Vec<WorkflowNode>,
     pub output: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[aliases(
+    ApiResponseString = ApiResponse<String>,
+    ApiResponseUser = ApiResponse<User>,
+    ApiResponseWorkspace = ApiResponse<Workspace>,
+    ApiResponseWorkspaceList = ApiResponse<Vec<Workspace>>,
+    ApiResponseWorkflowResponse = ApiResponse<WorkflowResponse>,
+    ApiResponsePasetoLoginResponse = ApiResponse<PasetoLoginResponse>,
+    ApiResponseGoogleUserInfo = ApiResponse<GoogleUserInfo>,
+    ApiResponseValueList = ApiResponse<Vec<serde_json::Value>>,
+    ApiResponseAsset = ApiResponse<AssetResponse>,
+)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub message: String,
 }
 
+/// Generated OpenAPI document for the routes below. `/api-docs/openapi.json` serves this
+/// and the Swagger UI is mounted at `/swagger-ui`.
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        health_check,
+        create_user,
+        get_workspaces,
+        create_workspace,
+        upload_workspace_avatar,
+        process_workflow,
+        get_openrouter_models,
+        paseto_login,
+        google_verify,
+    ),
+    components(schemas(
+        CreateUser,
+        CreateWorkspace,
+        WorkflowRequest,
+        WorkflowResponse,
+        User,
+        Workspace,
+        WorkflowNode,
+        PasetoLoginRequest,
+        PasetoLoginResponse,
+        GoogleUserInfo,
+        AssetResponse,
+        ApiResponseString,
+        ApiResponseUser,
+        ApiResponseWorkspace,
+        ApiResponseWorkspaceList,
+        ApiResponseWorkflowResponse,
+        ApiResponsePasetoLoginResponse,
+        ApiResponseGoogleUserInfo,
+        ApiResponseValueList,
+        ApiResponseAsset,
+    )),
+    modifiers(&SecurityAddon),
+    tags((name = "blabout", description = "Blabout backend API"))
+)]
+pub struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "paseto_bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("PASETO")
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Per-execution channel registry backing the `/ws` live-progress protocol: each workflow
+/// execution gets its own broadcast channel so any number of subscribers can watch the same
+/// run without interfering with others.
+pub type WorkflowChannels = Arc<Mutex<HashMap<Uuid, broadcast::Sender<WorkflowNode>>>>;
+
+/// Pending authorization-code + PKCE attempts, keyed by the `state` nonce handed to the
+/// provider. Entries are claimed (removed) by `/auth/:provider/callback` and are rejected
+/// as stale after `OAUTH_PENDING_TTL_MINUTES`; stale unclaimed entries are pruned whenever
+/// `/auth/:provider/login` stashes a new attempt.
+pub type OAuthPendingStore = Arc<Mutex<HashMap<String, oauth::PendingAuthorization>>>;
+
 // Application state
 #[derive(Clone)]
 pub struct AppState {
@@ -58,22 +151,68 @@ pub struct AppState {
     pub ai_service: AiService,
     pub gcp_project_id: String,
     pub paseto_keys: auth_paseto::PasetoKeys,
+    pub workflow_channels: WorkflowChannels,
+    pub oauth_pending: OAuthPendingStore,
+}
+
+/// Returns the broadcast sender for `execution_id`, creating its channel on first use.
+async fn workflow_channel(state: &AppState, execution_id: Uuid) -> broadcast::Sender<WorkflowNode> {
+    let mut channels = state.workflow_channels.lock().await;
+    channels
+        .entry(execution_id)
+        .or_insert_with(|| broadcast::channel(64).0)
+        .clone()
+}
+
+/// Drops `execution_id`'s entry once its execution reaches a terminal state, so
+/// `workflow_channels` doesn't grow without bound for the life of the process. A client that
+/// subscribes afterwards gets a fresh, silent channel rather than the finished run's history.
+async fn evict_workflow_channel(state: &AppState, execution_id: Uuid) {
+    state.workflow_channels.lock().await.remove(&execution_id);
 }
 
 // Validate PASETO v4.public token and return claims
-async fn validate_paseto(headers: HeaderMap, keys: &auth_paseto::PasetoKeys) -> Result<auth_paseto::PasetoClaims, StatusCode> {
+async fn validate_paseto(headers: HeaderMap, keys: &auth_paseto::PasetoKeys) -> Result<auth_paseto::PasetoClaims, ApiError> {
     let auth_header = headers
         .get("authorization")
-        .ok_or(StatusCode::UNAUTHORIZED)?
+        .ok_or(ApiError::MissingToken)?
         .to_str()
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        .map_err(|_| ApiError::InvalidToken)?;
 
-    if !auth_header.starts_with("Bearer ") { return Err(StatusCode::UNAUTHORIZED); }
+    if !auth_header.starts_with("Bearer ") { return Err(ApiError::MissingToken); }
     let token = &auth_header[7..];
-    auth_paseto::verify_v4_public(keys, token).map_err(|_| StatusCode::UNAUTHORIZED)
+    Ok(auth_paseto::verify_v4_public(keys, token, None, None, &auth_paseto::ValidationOptions::default())?)
+}
+
+/// Validates the PASETO, checks the claim carries `required_scope`, and resolves `claims.sub`
+/// to the internal `User` row so handlers operate on the real owner rather than a placeholder.
+async fn authorize(
+    headers: HeaderMap,
+    state: &AppState,
+    required_scope: &str,
+) -> Result<User, ApiError> {
+    let claims = validate_paseto(headers, &state.paseto_keys).await?;
+    let has_scope = claims
+        .scope
+        .as_ref()
+        .map(|scopes| scopes.iter().any(|s| s == required_scope))
+        .unwrap_or(false);
+    if !has_scope {
+        return Err(ApiError::Unauthorized);
+    }
+
+    User::find_by_provider_id(&state.db_pool, &claims.sub)
+        .await?
+        .ok_or(ApiError::InvalidToken)
 }
 
 // Handlers
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "blabout",
+    responses((status = 200, description = "Service is healthy", body = ApiResponseString))
+)]
 async fn health_check() -> Json<ApiResponse<String>> {
     Json(ApiResponse {
         success: true,
@@ -91,13 +230,18 @@ async fn get_users(State(_state): State<AppState>) -> Json<ApiResponse<Vec<User>
     })
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/users",
+    tag = "blabout",
+    request_body = CreateUser,
+    responses((status = 200, description = "User created", body = ApiResponseUser))
+)]
 async fn create_user(
     State(state): State<AppState>,
     Json(payload): Json<CreateUser>,
-) -> Result<Json<ApiResponse<User>>, StatusCode> {
-    let user = User::create(&state.db_pool, payload.email, payload.name, payload.auth_provider_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<Json<ApiResponse<User>>, ApiError> {
+    let user = User::create(&state.db_pool, payload.email, payload.name, payload.auth_provider_id).await?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -109,49 +253,83 @@ async fn create_user(
 async fn get_user(
     State(_state): State<AppState>,
     Path(_user_id): Path<Uuid>,
-) -> impl IntoResponse {
+) -> Result<Json<ApiResponse<User>>, ApiError> {
     // Users are managed through Kinde auth, not stored separately
-    StatusCode::NOT_FOUND
+    Err(ApiError::NotFound)
 }
 
 // WebSocket handler for real-time features
 async fn websocket_handler(
     ws: WebSocketUpgrade,
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(handle_socket)
+    ws.on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+/// Client-initiated messages for the `/ws` live-progress protocol.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsClientMessage {
+    Subscribe { execution_id: Uuid },
 }
 
-async fn handle_socket(mut socket: axum::extract::ws::WebSocket) {
+/// Once subscribed to an execution, pushes a `WorkflowNode` JSON frame every time that
+/// node's status changes, so the frontend can render the graph live.
+async fn handle_socket(mut socket: axum::extract::ws::WebSocket, state: AppState) {
     use axum::extract::ws::Message;
-    
-    while let Some(msg) = socket.recv().await {
-        if let Ok(msg) = msg {
-            match msg {
-                Message::Text(text) => {
-                    info!("Received: {}", text);
-                    // Echo back for now
-                    if socket.send(Message::Text(format!("Echo: {}", text))).await.is_err() {
+
+    let mut node_rx: Option<broadcast::Receiver<WorkflowNode>> = None;
+
+    loop {
+        let next_node = async {
+            match node_rx.as_mut() {
+                Some(rx) => Some(rx.recv().await),
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            incoming = socket.recv() => {
+                match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        match serde_json::from_str::<WsClientMessage>(&text) {
+                            Ok(WsClientMessage::Subscribe { execution_id }) => {
+                                info!("WebSocket subscribed to workflow execution {}", execution_id);
+                                node_rx = Some(workflow_channel(&state, execution_id).await.subscribe());
+                            }
+                            Err(e) => warn!("Ignoring unrecognized WebSocket message: {}", e),
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        info!("WebSocket connection closed");
                         break;
                     }
+                    Some(Err(_)) => break,
+                    _ => {}
                 }
-                Message::Binary(_) => {
-                    info!("Received binary data");
-                }
-                Message::Close(_) => {
-                    info!("WebSocket connection closed");
-                    break;
+            }
+            node = next_node => {
+                match node {
+                    Some(Ok(node)) => {
+                        let frame = serde_json::to_string(&node).unwrap_or_default();
+                        if socket.send(Message::Text(frame)).await.is_err() {
+                            break;
+                        }
+                    }
+                    // The execution finished and `evict_workflow_channel` dropped the sender.
+                    // Stop polling this receiver so the arm parks on `pending()` instead of
+                    // resolving immediately forever.
+                    Some(Err(broadcast::error::RecvError::Closed)) => node_rx = None,
+                    Some(Err(broadcast::error::RecvError::Lagged(_))) => {}
+                    None => {}
                 }
-                _ => {}
             }
-        } else {
-            break;
         }
     }
 }
 
 // Google OAuth verification (minimal): reads Authorization: Bearer <access_token> and returns Google userinfo
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct GoogleUserInfo {
     pub sub: String,
     pub email: Option<String>,
@@ -162,22 +340,27 @@ pub struct GoogleUserInfo {
 }
 
 // Reusable helper: read bearer token from headers, call Google userinfo, parse JSON
-async fn require_google_user(headers: &HeaderMap, client: &reqwest::Client) -> Result<GoogleUserInfo, StatusCode> {
-    let auth_header = headers.get("authorization").ok_or(StatusCode::UNAUTHORIZED)?
-        .to_str().map_err(|_| StatusCode::UNAUTHORIZED)?;
-    if !auth_header.starts_with("Bearer ") { return Err(StatusCode::UNAUTHORIZED); }
+async fn require_google_user(headers: &HeaderMap, client: &reqwest::Client) -> Result<GoogleUserInfo, ApiError> {
+    let auth_header = headers.get("authorization").ok_or(ApiError::MissingToken)?
+        .to_str().map_err(|_| ApiError::InvalidToken)?;
+    if !auth_header.starts_with("Bearer ") { return Err(ApiError::MissingToken); }
     let token = &auth_header[7..];
     let resp = client
         .get("https://www.googleapis.com/oauth2/v3/userinfo")
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|_| StatusCode::BAD_GATEWAY)?;
-    if !resp.status().is_success() { return Err(StatusCode::UNAUTHORIZED); }
-    resp.json::<GoogleUserInfo>().await.map_err(|_| StatusCode::BAD_GATEWAY)
+        .await?;
+    if !resp.status().is_success() { return Err(ApiError::InvalidToken); }
+    Ok(resp.json::<GoogleUserInfo>().await?)
 }
 
-async fn google_verify(headers: HeaderMap) -> Result<Json<ApiResponse<GoogleUserInfo>>, StatusCode> {
+#[utoipa::path(
+    get,
+    path = "/auth/google/verify",
+    tag = "blabout",
+    responses((status = 200, description = "Google access token verified", body = ApiResponseGoogleUserInfo))
+)]
+async fn google_verify(headers: HeaderMap) -> Result<Json<ApiResponse<GoogleUserInfo>>, ApiError> {
     let client = reqwest::Client::new();
     let info = require_google_user(&headers, &client).await?;
     Ok(Json(ApiResponse { success: true, data: Some(info), message: "Google token verified".to_string() }))
@@ -185,46 +368,266 @@ async fn google_verify(headers: HeaderMap) -> Result<Json<ApiResponse<GoogleUser
 
 
 // PASETO login: exchange Google access token for a PASETO v4.public
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct PasetoLoginRequest { pub access_token: String }
 
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PasetoLoginResponse { pub token: String, pub refresh_token: String, pub claims: PasetoClaims }
+
+#[derive(Serialize, Deserialize)]
+pub struct PasetoRefreshRequest { pub refresh_token: String }
+
 #[derive(Serialize, Deserialize)]
-pub struct PasetoLoginResponse { pub token: String, pub claims: PasetoClaims }
+pub struct PasetoLogoutRequest { pub refresh_token: String }
+
+/// Sessions back refresh tokens and live independently of the access token's minutes-scale
+/// lifetime; `SESSION_TTL_DAYS` lets operators tune how long a refresh token stays valid.
+fn session_ttl() -> chrono::Duration {
+    let days: i64 = std::env::var("SESSION_TTL_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    chrono::Duration::days(days)
+}
+
+/// Scopes granted to a user's own session: full read/write over their own workspaces.
+const DEFAULT_USER_SCOPE: &[&str] = &["workspaces:read", "workspaces:write"];
+
+/// Issues a PASETO + refresh token pair for `user_id` and persists the refresh token's hash
+/// as a new session row.
+async fn issue_session(
+    state: &AppState,
+    user_id: Uuid,
+    sub: String,
+    email: Option<String>,
+    name: Option<String>,
+) -> Result<PasetoLoginResponse, ApiError> {
+    let scope = DEFAULT_USER_SCOPE.iter().map(|s| s.to_string()).collect();
+    let claims = build_default_claims(sub, email, name, scope);
+    let token = issue_v4_public(&state.paseto_keys, &claims, None, None)
+        .map_err(|e| ApiError::InternalError(anyhow::anyhow!(e)))?;
+
+    let (refresh_token, token_hash) = auth_paseto::generate_refresh_token();
+    db::Session::create(&state.db_pool, user_id, &token_hash, session_ttl()).await?;
+
+    Ok(PasetoLoginResponse { token, refresh_token, claims })
+}
 
+#[utoipa::path(
+    post,
+    path = "/auth/paseto/login",
+    tag = "blabout",
+    request_body = PasetoLoginRequest,
+    responses((status = 200, description = "PASETO access + refresh token issued", body = ApiResponsePasetoLoginResponse))
+)]
 async fn paseto_login(
     State(state): State<AppState>,
     Json(payload): Json<PasetoLoginRequest>,
-) -> Result<Json<ApiResponse<PasetoLoginResponse>>, StatusCode> {
-    if payload.access_token.is_empty() { return Err(StatusCode::BAD_REQUEST); }
+) -> Result<Json<ApiResponse<PasetoLoginResponse>>, ApiError> {
+    if payload.access_token.is_empty() { return Err(ApiError::MissingCredentials); }
     let client = reqwest::Client::new();
     // Emulate Authorization header for reuse
     let mut hdrs = HeaderMap::new();
-    hdrs.insert("authorization", format!("Bearer {}", payload.access_token).parse().map_err(|_| StatusCode::BAD_REQUEST)?);
+    hdrs.insert(
+        "authorization",
+        format!("Bearer {}", payload.access_token)
+            .parse()
+            .map_err(|_| ApiError::MissingCredentials)?,
+    );
     let info = require_google_user(&hdrs, &client).await?;
 
-    let claims = build_default_claims(info.sub.clone(), info.email.clone(), info.name.clone());
-    let token = issue_v4_public(&state.paseto_keys, &claims).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let user = match User::find_by_provider_id(&state.db_pool, &info.sub).await? {
+        Some(user) => user,
+        None => {
+            User::create(
+                &state.db_pool,
+                info.email.clone().unwrap_or_default(),
+                info.name.clone().unwrap_or_default(),
+                info.sub.clone(),
+            )
+            .await?
+        }
+    };
+
+    let response = issue_session(&state, user.id, info.sub.clone(), info.email.clone(), info.name.clone()).await?;
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(PasetoLoginResponse { token, claims }),
+        data: Some(response),
+        message: "PASETO issued".to_string(),
+    }))
+}
+
+async fn paseto_refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<PasetoRefreshRequest>,
+) -> Result<Json<ApiResponse<PasetoLoginResponse>>, ApiError> {
+    let token_hash = auth_paseto::hash_refresh_token(&payload.refresh_token).map_err(|_| ApiError::InvalidToken)?;
+    let session = db::Session::find_by_token_hash(&state.db_pool, &token_hash)
+        .await?
+        .ok_or(ApiError::InvalidToken)?;
+
+    if session.revoked || session.expires_at < chrono::Utc::now() {
+        return Err(ApiError::InvalidToken);
+    }
+    // Rotate: revoke the used session so a stolen refresh token can't be replayed.
+    db::Session::revoke(&state.db_pool, session.id).await?;
+
+    let user = User::find_by_id(&state.db_pool, session.user_id)
+        .await?
+        .ok_or(ApiError::InvalidToken)?;
+
+    let response = issue_session(
+        &state,
+        user.id,
+        user.auth_provider_id.clone(),
+        Some(user.email.clone()),
+        Some(user.name.clone()),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(response),
+        message: "PASETO refreshed".to_string(),
+    }))
+}
+
+async fn paseto_logout(
+    State(state): State<AppState>,
+    Json(payload): Json<PasetoLogoutRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let token_hash = auth_paseto::hash_refresh_token(&payload.refresh_token).map_err(|_| ApiError::InvalidToken)?;
+    if let Some(session) = db::Session::find_by_token_hash(&state.db_pool, &token_hash).await? {
+        db::Session::revoke(&state.db_pool, session.id).await?;
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: None,
+        message: "Logged out".to_string(),
+    }))
+}
+
+/// Starts a server-side authorization-code + PKCE flow for `:provider` by generating a
+/// `code_verifier`/`code_challenge` pair and a `state` nonce, stashing them until the callback
+/// arrives, and redirecting the browser to the provider's authorize URL.
+async fn oauth_login(
+    Path(provider): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let oauth_provider = oauth::provider_by_name(&provider).ok_or(ApiError::NotFound)?;
+
+    let pkce = oauth::generate_pkce();
+    let state_nonce = oauth::generate_state();
+
+    {
+        let mut pending = state.oauth_pending.lock().await;
+        let now = chrono::Utc::now();
+        pending.retain(|_, p| now - p.created_at <= chrono::Duration::minutes(OAUTH_PENDING_TTL_MINUTES));
+        pending.insert(
+            state_nonce.clone(),
+            oauth::PendingAuthorization {
+                provider: provider.clone(),
+                code_verifier: pkce.verifier,
+                created_at: chrono::Utc::now(),
+            },
+        );
+    }
+
+    let mut authorize_url = reqwest::Url::parse(oauth_provider.authorize_url())
+        .map_err(|e| ApiError::InternalError(anyhow::anyhow!(e)))?;
+    authorize_url
+        .query_pairs_mut()
+        .append_pair("client_id", oauth_provider.client_id())
+        .append_pair("redirect_uri", oauth_provider.redirect_uri())
+        .append_pair("response_type", "code")
+        .append_pair("scope", oauth_provider.scopes())
+        .append_pair("state", &state_nonce)
+        .append_pair("code_challenge", &pkce.challenge)
+        .append_pair("code_challenge_method", "S256");
+
+    Ok(axum::response::Redirect::to(authorize_url.as_str()))
+}
+
+#[derive(Deserialize)]
+struct OAuthCallbackQuery {
+    code: String,
+    state: String,
+}
+
+/// How long a `/login` attempt's stashed PKCE verifier stays claimable before the callback
+/// must be rejected as stale.
+const OAUTH_PENDING_TTL_MINUTES: i64 = 10;
+
+/// Completes the authorization-code flow: validates `state` against the stashed pending
+/// entry, exchanges `code` for an access token using the matching PKCE verifier, fetches the
+/// provider's userinfo, and issues a PASETO the same way `paseto_login` does for the implicit
+/// Google flow.
+async fn oauth_callback(
+    Path(provider): Path<String>,
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<OAuthCallbackQuery>,
+) -> Result<Json<ApiResponse<PasetoLoginResponse>>, ApiError> {
+    let oauth_provider = oauth::provider_by_name(&provider).ok_or(ApiError::NotFound)?;
+
+    let pending = {
+        let mut pending = state.oauth_pending.lock().await;
+        pending.remove(&query.state)
+    }
+    .ok_or(ApiError::InvalidToken)?;
+
+    if pending.provider != provider {
+        return Err(ApiError::InvalidToken);
+    }
+    if chrono::Utc::now() - pending.created_at > chrono::Duration::minutes(OAUTH_PENDING_TTL_MINUTES) {
+        return Err(ApiError::InvalidToken);
+    }
+
+    let client = reqwest::Client::new();
+    let access_token = oauth_provider
+        .exchange_code(&client, &query.code, &pending.code_verifier)
+        .await?;
+    let info = oauth_provider.fetch_user(&client, &access_token).await?;
+
+    let user = match User::find_by_provider_id(&state.db_pool, &info.sub).await? {
+        Some(user) => user,
+        None => {
+            User::create(
+                &state.db_pool,
+                info.email.clone().unwrap_or_default(),
+                info.name.clone().unwrap_or_default(),
+                info.sub.clone(),
+            )
+            .await?
+        }
+    };
+
+    let response = issue_session(&state, user.id, info.sub.clone(), info.email.clone(), info.name.clone()).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(response),
         message: "PASETO issued".to_string(),
     }))
 }
 
 // Workspace handlers
+#[utoipa::path(
+    get,
+    path = "/api/workspaces",
+    tag = "blabout",
+    security(("paseto_bearer" = [])),
+    responses((status = 200, description = "Workspaces for the authenticated user", body = ApiResponseWorkspaceList))
+)]
 async fn get_workspaces(
     headers: HeaderMap,
     State(state): State<AppState>,
-) -> Result<Json<ApiResponse<Vec<Workspace>>>, StatusCode> {
-let _claims = validate_paseto(headers, &state.paseto_keys).await?;
-    // TODO: map claims.sub (auth_provider_id) -> internal user_id via DB
-    let user_id = Uuid::new_v4(); // placeholder: replace with lookup
-    
-    let workspaces = Workspace::find_by_user_id(&state.db_pool, user_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
+) -> Result<Json<ApiResponse<Vec<Workspace>>>, ApiError> {
+    let user = authorize(headers, &state, "workspaces:read").await?;
+
+    let workspaces = Workspace::find_by_user_id(&state.db_pool, user.id).await?;
+
     Ok(Json(ApiResponse {
         success: true,
         data: Some(workspaces),
@@ -232,18 +635,22 @@ let _claims = validate_paseto(headers, &state.paseto_keys).await?;
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/workspaces",
+    tag = "blabout",
+    security(("paseto_bearer" = [])),
+    request_body = CreateWorkspace,
+    responses((status = 200, description = "Workspace created", body = ApiResponseWorkspace))
+)]
 async fn create_workspace(
     headers: HeaderMap,
     State(state): State<AppState>,
     Json(payload): Json<CreateWorkspace>,
-) -> Result<Json<ApiResponse<Workspace>>, StatusCode> {
-let _claims = validate_paseto(headers, &state.paseto_keys).await?;
-    // TODO: map claims.sub (auth_provider_id) -> internal user_id via DB
-    let user_id = Uuid::new_v4(); // placeholder: replace with lookup
-    
-    let workspace = Workspace::create(&state.db_pool, payload.name, user_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<Json<ApiResponse<Workspace>>, ApiError> {
+    let user = authorize(headers, &state, "workspaces:write").await?;
+
+    let workspace = Workspace::create(&state.db_pool, payload.name, user.id).await?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -252,64 +659,196 @@ let _claims = validate_paseto(headers, &state.paseto_keys).await?;
     }))
 }
 
+// Workspace avatar / asset handlers
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AssetResponse {
+    pub id: Uuid,
+    pub content_type: String,
+}
+
+/// Side length, in pixels, a workspace avatar is normalized to before storage.
+const AVATAR_SIZE: u32 = 256;
+
+/// Caps how large an uploaded avatar part may be, configurable via `MAX_UPLOAD_BYTES`
+/// (defaults to 5 MiB).
+fn max_upload_bytes() -> usize {
+    std::env::var("MAX_UPLOAD_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5 * 1024 * 1024)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/workspaces/{id}/avatar",
+    tag = "blabout",
+    security(("paseto_bearer" = [])),
+    responses((status = 200, description = "Workspace avatar uploaded", body = ApiResponseAsset))
+)]
+async fn upload_workspace_avatar(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(workspace_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<ApiResponse<AssetResponse>>, ApiError> {
+    let user = authorize(headers, &state, "workspaces:write").await?;
+
+    let workspace = Workspace::find_by_id(&state.db_pool, workspace_id)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    if workspace.user_id != user.id {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let max_bytes = max_upload_bytes();
+    let mut upload: Option<(String, Option<String>, Vec<u8>)> = None;
+
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() != Some("avatar") {
+            continue;
+        }
+        let file_name = field.file_name().unwrap_or("avatar").to_string();
+        let declared_content_type = field.content_type().map(|s| s.to_string());
+        let data = field.bytes().await?;
+        if data.len() > max_bytes {
+            return Err(ApiError::PayloadTooLarge);
+        }
+        upload = Some((file_name, declared_content_type, data.to_vec()));
+        break;
+    }
+
+    let (file_name, declared_content_type, bytes) = upload.ok_or(ApiError::MissingCredentials)?;
+
+    let looks_like_image = declared_content_type
+        .as_deref()
+        .map(|ct| ct.starts_with("image/"))
+        .unwrap_or(false)
+        || mime_guess::from_path(&file_name).first_or_octet_stream().type_() == "image";
+    if !looks_like_image {
+        return Err(ApiError::UnsupportedMediaType(
+            declared_content_type.unwrap_or_else(|| "unknown".to_string()),
+        ));
+    }
+
+    // Check the declared dimensions before fully decoding: a small, highly-compressed image
+    // can still claim an enormous pixel grid and blow up memory on decode otherwise.
+    const MAX_SOURCE_DIMENSION: u32 = 8192;
+    let (width, height) = image::io::Reader::new(std::io::Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|_| ApiError::UnsupportedMediaType("could not decode image".to_string()))?
+        .into_dimensions()
+        .map_err(|_| ApiError::UnsupportedMediaType("could not decode image".to_string()))?;
+    if width > MAX_SOURCE_DIMENSION || height > MAX_SOURCE_DIMENSION {
+        return Err(ApiError::UnsupportedMediaType("image dimensions too large".to_string()));
+    }
+
+    let decoded = image::load_from_memory(&bytes)
+        .map_err(|_| ApiError::UnsupportedMediaType("could not decode image".to_string()))?;
+
+    // Re-encoding to PNG strips any embedded metadata (EXIF, ICC profiles, ...) and the
+    // fixed 256x256 crop caps storage size regardless of what was uploaded.
+    let normalized = decoded.resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, image::imageops::FilterType::Lanczos3);
+    let mut png_bytes = Vec::new();
+    normalized
+        .write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| ApiError::InternalError(anyhow::anyhow!(e)))?;
+
+    let asset = db::Asset::create(&state.db_pool, workspace.id, "workspace_avatar", png_bytes, "image/png").await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(AssetResponse { id: asset.id, content_type: asset.content_type }),
+        message: "Workspace avatar uploaded".to_string(),
+    }))
+}
+
+/// Streams a stored asset's raw bytes with its original `Content-Type`.
+async fn get_asset(
+    State(state): State<AppState>,
+    Path(asset_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let asset = db::Asset::find(&state.db_pool, asset_id).await?.ok_or(ApiError::NotFound)?;
+    Ok(([(header::CONTENT_TYPE, asset.content_type)], asset.bytes))
+}
+
 // AI Workflow handlers
+#[utoipa::path(
+    post,
+    path = "/api/workflow/process",
+    tag = "blabout",
+    security(("paseto_bearer" = [])),
+    request_body = WorkflowRequest,
+    responses((status = 200, description = "Workflow started; subscribe over /ws with the returned execution_id for live node updates", body = ApiResponseWorkflowResponse))
+)]
 async fn process_workflow(
     headers: HeaderMap,
     State(state): State<AppState>,
     Json(payload): Json<WorkflowRequest>,
-) -> Result<Json<ApiResponse<WorkflowResponse>>, StatusCode> {
-    let _claims = validate_paseto(headers, &state.paseto_keys).await?;
-    
+) -> Result<Json<ApiResponse<WorkflowResponse>>, ApiError> {
+    let user = authorize(headers, &state, "workspaces:write").await?;
+    let workspace = Workspace::find_by_id(&state.db_pool, payload.workspace_id)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    if workspace.user_id != user.id {
+        return Err(ApiError::Unauthorized);
+    }
+
     info!("Processing workflow for message: {}", payload.message);
-    
-    // Use the AI service to process the workflow with OpenRouter and fallbacks
-    match state.ai_service.process_workflow(&payload.message).await {
-        Ok(nodes) => {
-            let output = nodes
-                .iter()
-                .filter(|n| n.status == "completed" && n.output.is_some())
-                .map(|n| format!("{}: {}", n.title, n.output.as_ref().unwrap_or(&"No output".to_string())))
-                .collect::<Vec<_>>()
-                .join("\n\n");
-            
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(WorkflowResponse { nodes, output }),
-                message: "Workflow processing completed successfully".to_string(),
-            }))
-        }
-        Err(e) => {
-            info!("Workflow processing failed: {}", e);
-            
-            // Return error nodes for visualization
-            let error_nodes = vec![
-                WorkflowNode {
-                    id: Uuid::new_v4().to_string(),
-                    node_type: "error".to_string(),
-                    status: "error".to_string(),
-                    title: "Processing Error".to_string(),
-                    description: "Failed to process workflow with AI service".to_string(),
-                    output: Some(format!("Error: {}", e)),
-                },
-            ];
-            
-            Ok(Json(ApiResponse {
-                success: false,
-                data: Some(WorkflowResponse { 
-                    nodes: error_nodes, 
-                    output: format!("Failed to process workflow: {}", e) 
-                }),
-                message: "Workflow processing failed".to_string(),
-            }))
+
+    let execution = db::WorkflowExecution::create(&state.db_pool, payload.workspace_id, payload.message.clone()).await?;
+    let node_tx = workflow_channel(&state, execution.id).await;
+    db::WorkflowExecution::update_status(&state.db_pool, execution.id, "running", None).await?;
+
+    // Run the planner/coder/reviewer pipeline in the background and hand back `execution_id`
+    // immediately, so a client can open the WebSocket and send `{"type":"subscribe"}` before
+    // any node is published instead of only after the whole pipeline has already finished.
+    let execution_id = execution.id;
+    let message = payload.message.clone();
+    tokio::spawn(async move {
+        let result = state.ai_service.process_workflow(&message, Some(&node_tx)).await;
+
+        let (status, persisted_output) = match &result {
+            Ok(nodes) => {
+                let output = nodes
+                    .iter()
+                    .filter(|n| n.status == "completed" && n.output.is_some())
+                    .map(|n| format!("{}: {}", n.title, n.output.as_ref().unwrap_or(&"No output".to_string())))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                ("completed", output)
+            }
+            Err(e) => {
+                info!("Workflow processing failed: {}", e);
+                ("error", format!("Error: {}", e))
+            }
+        };
+
+        if let Err(e) = db::WorkflowExecution::update_status(&state.db_pool, execution_id, status, Some(persisted_output)).await {
+            warn!("Failed to persist final status for workflow execution {}: {}", execution_id, e);
         }
-    }
+
+        evict_workflow_channel(&state, execution_id).await;
+    });
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(WorkflowResponse { execution_id, nodes: Vec::new(), output: String::new() }),
+        message: "Workflow processing started".to_string(),
+    }))
 }
 
 // OpenRouter models endpoint
+#[utoipa::path(
+    get,
+    path = "/api/models",
+    tag = "blabout",
+    security(("paseto_bearer" = [])),
+    responses((status = 200, description = "Available models for chat completions", body = ApiResponseValueList))
+)]
 async fn get_openrouter_models(
     headers: HeaderMap,
     State(state): State<AppState>,
-) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>, StatusCode> {
+) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>, ApiError> {
     let _claims = validate_paseto(headers, &state.paseto_keys).await?;
     
     match state.ai_service.list_available_models().await {
@@ -369,9 +908,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_pool = db::create_pool(&database_url).await?;
     db::init_schema(&db_pool).await?;
 
-    // Initialize AI service
+    // Initialize AI service. Models come from AI_MODEL_CONFIG_JSON so operators can add,
+    // reprioritize, or repoint fallback models without recompiling.
     let openrouter_key = std::env::var("OPENROUTER_API_KEY").unwrap_or_default();
-    let ai_service = AiService::new(openrouter_key);
+    let ai_service = AiService::new(openrouter_key, AiService::load_model_configs_from_env());
 
     // Initialize application state
     // Load PASETO keys from env (base64url, no padding) – store via Secret Manager
@@ -385,6 +925,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         ai_service,
         gcp_project_id: std::env::var("GOOGLE_CLOUD_PROJECT_ID").unwrap_or_default(),
         paseto_keys,
+        workflow_channels: Arc::new(Mutex::new(HashMap::new())),
+        oauth_pending: Arc::new(Mutex::new(HashMap::new())),
     };
 
     // Build our application with routes
@@ -394,11 +936,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/api/users", get(get_users).post(create_user))
         .route("/api/users/:id", get(get_user))
         .route("/api/workspaces", get(get_workspaces).post(create_workspace))
+        // Lift axum's default 2 MB body limit so the `MAX_UPLOAD_BYTES` cap is the one enforced.
+        .route(
+            "/api/workspaces/:id/avatar",
+            post(upload_workspace_avatar)
+                .layer(axum::extract::DefaultBodyLimit::max(max_upload_bytes() + 64 * 1024)),
+        )
+        .route("/api/assets/:id", get(get_asset))
         .route("/api/workflow/process", post(process_workflow))
         .route("/api/models", get(get_openrouter_models))
         .route("/ws", get(websocket_handler))
         .route("/auth/paseto/login", post(paseto_login))
+        .route("/auth/paseto/refresh", post(paseto_refresh))
+        .route("/auth/paseto/logout", post(paseto_logout))
         .route("/auth/google/verify", get(google_verify))
+        .route("/auth/:provider/login", get(oauth_login))
+        .route("/auth/:provider/callback", get(oauth_callback))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .layer(CorsLayer::permissive())
         .with_state(app_state);
 
@@ -413,6 +967,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("📊 WebSocket endpoint: ws://localhost:{}/ws", port);
 info!("🔐 Auth endpoint: GET /auth/google/verify");
     info!("🔐 Auth endpoint: POST /auth/paseto/login");
+    info!("🔐 Auth endpoint: POST /auth/paseto/refresh");
+    info!("🔐 Auth endpoint: POST /auth/paseto/logout");
+    info!("🔐 Auth endpoint: GET /auth/:provider/login, GET /auth/:provider/callback");
+    info!("📚 API docs: GET /swagger-ui, GET /api-docs/openapi.json");
 
     axum::serve(listener, app).await?;
 
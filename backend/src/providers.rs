@@ -0,0 +1,498 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::ai_service::{AiResponse, ChatMessage};
+
+/// Backend-agnostic chat completion request, built from an `AiService` fallback entry.
+#[derive(Debug, Clone)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+    pub temperature: f32,
+    pub top_p: f32,
+}
+
+/// A backend capable of turning a `CompletionRequest` into an `AiResponse`. Implemented by
+/// OpenRouter, any OpenAI-compatible endpoint, and Google Vertex AI so a single fallback
+/// chain can span multiple providers.
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    async fn complete(&self, req: &CompletionRequest) -> Result<AiResponse>;
+
+    /// Streams incremental content chunks as they arrive. The default implementation rejects
+    /// streaming; providers that speak an SSE wire format override it.
+    async fn stream(&self, req: &CompletionRequest) -> Result<mpsc::Receiver<Result<String>>> {
+        let _ = req;
+        Err(anyhow!("provider does not support streaming"))
+    }
+
+    /// Completes a request with a `tools` JSON schema attached, returning the raw assistant
+    /// message (including any `tool_calls`) for the caller to interpret. The default
+    /// implementation rejects tool calling.
+    async fn complete_with_tools(&self, req: &CompletionRequest, tools: &[Value]) -> Result<Value> {
+        let _ = (req, tools);
+        Err(anyhow!("provider does not support tool calling"))
+    }
+}
+
+/// OpenRouter's chat-completions endpoint (the provider this service originally hardcoded).
+pub struct OpenRouterProvider {
+    client: Client,
+    api_key: String,
+}
+
+impl OpenRouterProvider {
+    pub fn new(client: Client, api_key: String) -> Self {
+        Self { client, api_key }
+    }
+}
+
+const OPENROUTER_HEADERS: &[(&str, &str)] = &[
+    ("HTTP-Referer", "https://blabout.com"),
+    ("X-Title", "Blabout AI Workspace"),
+];
+
+#[async_trait]
+impl ChatProvider for OpenRouterProvider {
+    async fn complete(&self, req: &CompletionRequest) -> Result<AiResponse> {
+        complete_openai_compatible(
+            &self.client,
+            "https://openrouter.ai/api/v1/chat/completions",
+            &self.api_key,
+            OPENROUTER_HEADERS,
+            req,
+            "OpenRouter",
+        )
+        .await
+    }
+
+    async fn stream(&self, req: &CompletionRequest) -> Result<mpsc::Receiver<Result<String>>> {
+        stream_openai_compatible(
+            &self.client,
+            "https://openrouter.ai/api/v1/chat/completions",
+            &self.api_key,
+            OPENROUTER_HEADERS,
+            req,
+        )
+        .await
+    }
+
+    async fn complete_with_tools(&self, req: &CompletionRequest, tools: &[Value]) -> Result<Value> {
+        complete_openai_compatible_with_tools(
+            &self.client,
+            "https://openrouter.ai/api/v1/chat/completions",
+            &self.api_key,
+            OPENROUTER_HEADERS,
+            req,
+            tools,
+        )
+        .await
+    }
+}
+
+/// Any endpoint that speaks the OpenAI chat-completions wire format, e.g. a self-hosted
+/// vLLM/Ollama server or a third-party OpenAI-compatible API.
+pub struct OpenAiCompatProvider {
+    client: Client,
+    api_base: String,
+    api_key: String,
+    extra_headers: Vec<(String, String)>,
+}
+
+impl OpenAiCompatProvider {
+    pub fn new(client: Client, api_base: String, api_key: String, extra_headers: Vec<(String, String)>) -> Self {
+        Self { client, api_base, api_key, extra_headers }
+    }
+}
+
+impl OpenAiCompatProvider {
+    fn headers(&self) -> Vec<(&str, &str)> {
+        self.extra_headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/chat/completions", self.api_base.trim_end_matches('/'))
+    }
+}
+
+#[async_trait]
+impl ChatProvider for OpenAiCompatProvider {
+    async fn complete(&self, req: &CompletionRequest) -> Result<AiResponse> {
+        complete_openai_compatible(&self.client, &self.endpoint(), &self.api_key, &self.headers(), req, "OpenAI-compatible").await
+    }
+
+    async fn stream(&self, req: &CompletionRequest) -> Result<mpsc::Receiver<Result<String>>> {
+        stream_openai_compatible(&self.client, &self.endpoint(), &self.api_key, &self.headers(), req).await
+    }
+
+    async fn complete_with_tools(&self, req: &CompletionRequest, tools: &[Value]) -> Result<Value> {
+        complete_openai_compatible_with_tools(&self.client, &self.endpoint(), &self.api_key, &self.headers(), req, tools).await
+    }
+}
+
+async fn complete_openai_compatible(
+    client: &Client,
+    endpoint: &str,
+    api_key: &str,
+    extra_headers: &[(&str, &str)],
+    req: &CompletionRequest,
+    provider_label: &str,
+) -> Result<AiResponse> {
+    let payload = json!({
+        "model": req.model,
+        "messages": req.messages,
+        "max_tokens": req.max_tokens,
+        "temperature": req.temperature,
+        "top_p": req.top_p,
+        "stream": false
+    });
+
+    let mut builder = client
+        .post(endpoint)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json");
+    for (k, v) in extra_headers {
+        builder = builder.header(*k, *v);
+    }
+
+    let response = builder.json(&payload).send().await?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow!("API error: {}", error_text));
+    }
+
+    let json: Value = response.json().await?;
+    let content = json
+        .get("choices")
+        .and_then(|choices| choices.get(0))
+        .and_then(|choice| choice.get("message"))
+        .and_then(|message| message.get("content"))
+        .and_then(|content| content.as_str())
+        .ok_or_else(|| anyhow!("Invalid response format"))?;
+
+    Ok(AiResponse {
+        content: content.to_string(),
+        model_used: req.model.clone(),
+        provider: provider_label.to_string(),
+    })
+}
+
+/// Outcome of reading SSE `data:` lines up to the next event worth surfacing.
+enum SseEvent {
+    Content(String),
+    Done,
+}
+
+/// Reads from `response` into `buf` until the next content delta, a `[DONE]` sentinel, or the
+/// stream ends, returning `Ok(None)` only when the body closed without either. Shared between
+/// the initial peek (so the caller can fall back to the next model before handing out a
+/// receiver) and the background task that keeps draining the same stream afterwards.
+async fn next_sse_event(response: &mut reqwest::Response, buf: &mut String) -> Result<Option<SseEvent>> {
+    loop {
+        while let Some(pos) = buf.find('\n') {
+            let line = buf[..pos].trim_end_matches('\r').to_string();
+            buf.drain(..=pos);
+
+            let Some(data) = line.strip_prefix("data: ") else { continue };
+            if data == "[DONE]" {
+                return Ok(Some(SseEvent::Done));
+            }
+
+            let event: Value = serde_json::from_str(data).map_err(|e| anyhow!("invalid SSE event: {}", e))?;
+            if let Some(delta) = event
+                .get("choices")
+                .and_then(|c| c.get(0))
+                .and_then(|c| c.get("delta"))
+                .and_then(|d| d.get("content"))
+                .and_then(|c| c.as_str())
+            {
+                return Ok(Some(SseEvent::Content(delta.to_string())));
+            }
+            // Role-only or finish_reason-only delta; keep scanning for the next line.
+        }
+
+        match response.chunk().await? {
+            Some(chunk) => buf.push_str(&String::from_utf8_lossy(&chunk)),
+            None => return Ok(None),
+        }
+    }
+}
+
+async fn stream_openai_compatible(
+    client: &Client,
+    endpoint: &str,
+    api_key: &str,
+    extra_headers: &[(&str, &str)],
+    req: &CompletionRequest,
+) -> Result<mpsc::Receiver<Result<String>>> {
+    let payload = json!({
+        "model": req.model,
+        "messages": req.messages,
+        "max_tokens": req.max_tokens,
+        "temperature": req.temperature,
+        "top_p": req.top_p,
+        "stream": true
+    });
+
+    let mut builder = client
+        .post(endpoint)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json");
+    for (k, v) in extra_headers {
+        builder = builder.header(*k, *v);
+    }
+
+    let mut response = builder.json(&payload).send().await?;
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow!("API error: {}", error_text));
+    }
+
+    // Peek until the first content delta (or a terminal error/EOF) before committing to this
+    // model, so a stream that errors before producing any output still falls back cleanly.
+    let mut buf = String::new();
+    let first_content = match next_sse_event(&mut response, &mut buf).await? {
+        Some(SseEvent::Content(content)) => content,
+        Some(SseEvent::Done) | None => return Err(anyhow!("stream ended before producing any content")),
+    };
+
+    let (tx, rx) = mpsc::channel(32);
+
+    tokio::spawn(async move {
+        if tx.send(Ok(first_content)).await.is_err() {
+            return;
+        }
+
+        loop {
+            match next_sse_event(&mut response, &mut buf).await {
+                Ok(Some(SseEvent::Content(content))) => {
+                    if tx.send(Ok(content)).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(Some(SseEvent::Done)) | Ok(None) => return,
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+async fn complete_openai_compatible_with_tools(
+    client: &Client,
+    endpoint: &str,
+    api_key: &str,
+    extra_headers: &[(&str, &str)],
+    req: &CompletionRequest,
+    tools: &[Value],
+) -> Result<Value> {
+    let mut payload = json!({
+        "model": req.model,
+        "messages": req.messages,
+        "max_tokens": req.max_tokens,
+        "temperature": req.temperature,
+        "top_p": req.top_p,
+        "stream": false
+    });
+    if !tools.is_empty() {
+        payload["tools"] = json!(tools);
+    }
+
+    let mut builder = client
+        .post(endpoint)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json");
+    for (k, v) in extra_headers {
+        builder = builder.header(*k, *v);
+    }
+
+    let response = builder.json(&payload).send().await?;
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow!("API error: {}", error_text));
+    }
+
+    let json: Value = response.json().await?;
+    json.get("choices")
+        .and_then(|choices| choices.get(0))
+        .and_then(|choice| choice.get("message"))
+        .cloned()
+        .ok_or_else(|| anyhow!("Invalid response format"))
+}
+
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+/// Native Google Vertex AI provider. Exchanges the ADC service-account JSON for a bearer
+/// token via the JWT-bearer grant and caches it until it's close to expiry.
+pub struct VertexAiProvider {
+    client: Client,
+    project_id: String,
+    location: String,
+    credentials_path: String,
+    cached_token: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+impl VertexAiProvider {
+    pub fn new(client: Client, project_id: String, location: String, credentials_path: String) -> Self {
+        Self {
+            client,
+            project_id,
+            location,
+            credentials_path,
+            cached_token: Mutex::new(None),
+        }
+    }
+
+    async fn bearer_token(&self) -> Result<String> {
+        let mut cached = self.cached_token.lock().await;
+        if let Some((token, expiry)) = cached.as_ref() {
+            if Utc::now() < *expiry - Duration::seconds(60) {
+                return Ok(token.clone());
+            }
+        }
+
+        let key_json = std::fs::read_to_string(&self.credentials_path)
+            .map_err(|e| anyhow!("failed to read ADC credentials file: {}", e))?;
+        let sa: ServiceAccountKey = serde_json::from_str(&key_json)?;
+
+        #[derive(Serialize)]
+        struct Claims {
+            iss: String,
+            scope: String,
+            aud: String,
+            iat: i64,
+            exp: i64,
+        }
+
+        let now = Utc::now();
+        let claims = Claims {
+            iss: sa.client_email.clone(),
+            scope: "https://www.googleapis.com/auth/cloud-platform".to_string(),
+            aud: sa.token_uri.clone(),
+            iat: now.timestamp(),
+            exp: (now + Duration::hours(1)).timestamp(),
+        };
+
+        let encoding_key = jsonwebtoken::EncodingKey::from_rsa_pem(sa.private_key.as_bytes())
+            .map_err(|e| anyhow!("invalid service-account private key: {}", e))?;
+        let assertion = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &claims,
+            &encoding_key,
+        )?;
+
+        let token_response: Value = self
+            .client
+            .post(&sa.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let access_token = token_response
+            .get("access_token")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("token exchange response missing access_token"))?
+            .to_string();
+        let expires_in = token_response.get("expires_in").and_then(|v| v.as_i64()).unwrap_or(3600);
+        let expiry = now + Duration::seconds(expires_in);
+
+        *cached = Some((access_token.clone(), expiry));
+        Ok(access_token)
+    }
+}
+
+#[async_trait]
+impl ChatProvider for VertexAiProvider {
+    async fn complete(&self, req: &CompletionRequest) -> Result<AiResponse> {
+        let token = self.bearer_token().await?;
+        let url = format!(
+            "https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models/{}:generateContent",
+            self.location, self.project_id, self.location, req.model
+        );
+
+        // Vertex has no "system" role in `contents`; system messages go in `systemInstruction`
+        // instead of being dropped, so the planner/coder/reviewer prompts still take effect.
+        let system_instruction = req
+            .messages
+            .iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let contents: Vec<Value> = req
+            .messages
+            .iter()
+            .filter(|m| m.role != "system")
+            .map(|m| {
+                json!({
+                    "role": if m.role == "assistant" { "model" } else { "user" },
+                    "parts": [{ "text": m.content }]
+                })
+            })
+            .collect();
+
+        let mut payload = json!({
+            "contents": contents,
+            "generationConfig": {
+                "maxOutputTokens": req.max_tokens,
+                "temperature": req.temperature,
+                "topP": req.top_p,
+            }
+        });
+        if !system_instruction.is_empty() {
+            payload["systemInstruction"] = json!({ "parts": [{ "text": system_instruction }] });
+        }
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(token)
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Vertex AI error: {}", error_text));
+        }
+
+        let json: Value = response.json().await?;
+        let content = json
+            .get("candidates")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("content"))
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.get(0))
+            .and_then(|p| p.get("text"))
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| anyhow!("Invalid Vertex AI response format"))?;
+
+        Ok(AiResponse {
+            content: content.to_string(),
+            model_used: req.model.clone(),
+            provider: "Google Vertex AI".to_string(),
+        })
+    }
+}